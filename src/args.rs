@@ -47,6 +47,56 @@ pub struct Args {
         default_value = "pass",
     )]
     pub monero_rpc_cred: String,
+    /// Monero RPC request timeout
+    #[arg(
+        long,
+        help = "Monero RPC request timeout in seconds.",
+        default_value = "120",
+    )]
+    pub monero_rpc_timeout: u64,
+    /// Monero RPC retries
+    #[arg(
+        long,
+        help = "Monero RPC retries when wallet-rpc can't be reached.",
+        default_value = "2",
+    )]
+    pub monero_rpc_retries: u32,
+    /// Number of monero-wallet-rpc processes
+    #[arg(
+        long,
+        help = "Number of monero-wallet-rpc processes, listening on consecutive ports from the rpc host port.",
+        default_value = "1",
+    )]
+    pub monero_rpc_pool: usize,
+    /// Path to the monero-wallet-rpc binary
+    #[arg(
+        long,
+        help = "Path to the monero-wallet-rpc binary.",
+        default_value = "./monero-wallet-rpc",
+    )]
+    pub monero_rpc_bin: String,
+    /// Directory monero-wallet-rpc keeps wallets in (--wallet-dir arg)
+    #[arg(
+        long,
+        help = "Directory monero-wallet-rpc keeps wallets in.",
+        default_value = "wallet",
+    )]
+    pub monero_wallet_dir: String,
+    /// Extra monero-wallet-rpc flags
+    #[arg(
+        long,
+        help = "Extra monero-wallet-rpc flags, separated by spaces.",
+        default_value = "",
+        allow_hyphen_values = true,
+    )]
+    pub monero_rpc_flags: String,
+    /// Seconds to wait for monero-wallet-rpc on start up
+    #[arg(
+        long,
+        help = "Seconds to wait for monero-wallet-rpc to answer on start up.",
+        default_value = "60",
+    )]
+    pub monero_rpc_ready_timeout: u64,
     /// Token expiration in minutes
     #[arg(
         short,
@@ -63,4 +113,116 @@ pub struct Args {
         default_value = "some-secret",
     )]
     pub jwt_secret_key: String,
+    /// Vendor bond in piconero
+    #[arg(
+        long,
+        help = "Set the bond a vendor must pay before activation (piconero, 0 disables).",
+        default_value = "0",
+    )]
+    pub vendor_bond: u128,
+    /// Marketplace wallet
+    #[arg(
+        long,
+        help = "Set the marketplace wallet filename used for bonds and commission.",
+        default_value = "market",
+    )]
+    pub market_wallet: String,
+    /// Admin xmr address
+    #[arg(
+        long,
+        help = "Set the xmr address allowed to perform admin actions.",
+        default_value = "",
+    )]
+    pub admin_address: String,
+    /// Product image directory
+    #[arg(
+        long,
+        help = "Set the directory product images are stored in.",
+        default_value = "images",
+    )]
+    pub image_dir: String,
+    /// Local exchange rate file
+    #[arg(
+        long,
+        help = "Set the JSON file of fiat rates (cents per XMR) used when no rate url is set.",
+        default_value = "rates.json",
+    )]
+    pub rate_file: String,
+    /// Exchange rate endpoint
+    #[arg(
+        long,
+        help = "Set an HTTP endpoint returning fiat rates (cents per XMR), fetched over the i2p proxy.",
+        default_value = "",
+    )]
+    pub rate_url: String,
+    /// I2P http proxy
+    #[arg(
+        long,
+        help = "Set the i2p http proxy used for outbound requests.",
+        default_value = "http://127.0.0.1:4444",
+    )]
+    pub i2p_proxy: String,
+    /// Funding confirmations
+    #[arg(
+        long,
+        help = "Set the confirmations an order funding transfer needs before the order is funded.",
+        default_value = "10",
+    )]
+    pub confirmations: u64,
+    /// Funding watcher interval
+    #[arg(
+        long,
+        help = "Set how often in seconds order wallets are checked for funding.",
+        default_value = "120",
+    )]
+    pub funding_interval: u64,
+    /// Unfunded order expiry
+    #[arg(
+        long,
+        help = "Set the hours an order has to be funded before it expires.",
+        default_value = "24",
+    )]
+    pub expire_hours: i64,
+    /// Shipping deadline
+    #[arg(
+        long,
+        help = "Set the days a vendor has to ship a funded order before the customer can be refunded.",
+        default_value = "7",
+    )]
+    pub ship_days: i64,
+    /// Auto-finalize window
+    #[arg(
+        long,
+        help = "Set the days after shipping before an undisputed order is released to the vendor.",
+        default_value = "14",
+    )]
+    pub finalize_days: i64,
+    /// Deadline job interval
+    #[arg(
+        long,
+        help = "Set how often in seconds order deadlines are checked.",
+        default_value = "600",
+    )]
+    pub deadline_interval: u64,
+    /// Dispute evidence directory
+    #[arg(
+        long,
+        help = "Set the directory encrypted dispute evidence files are stored in.",
+        default_value = "evidence",
+    )]
+    pub evidence_dir: String,
+    /// Default commission type
+    #[arg(
+        long,
+        help = "Set the default market commission type, percent or flat.",
+        default_value = "percent",
+    )]
+    pub commission_type: String,
+    /// Default commission
+    #[arg(
+        long,
+        help = "Set the default market commission, basis points for percent or piconero for flat.",
+        default_value = "0",
+    )]
+    pub commission: i64,
 }
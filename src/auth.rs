@@ -1,6 +1,6 @@
-use crate::{args, customer, db, models::*, monero, utils};
+use crate::{args, customer, db, mediator, models::*, monero, utils, vendor};
 use clap::Parser;
-use log::{debug, info};
+use log::{debug, error, info};
 
 use rocket::http::Status;
 use rocket::outcome::Outcome;
@@ -12,15 +12,17 @@ use jwt::{AlgorithmType, Header, SignWithKey, Token, VerifyWithKey};
 use sha2::Sha384;
 use std::collections::BTreeMap;
 
-/// Determine customer or vendor login
+/// Determine customer, vendor or mediator login
 pub async fn get_login
 (address: String, aid: String, corv: String, cvid: String, signature: String) -> Authorization {
     info!("verify {} login", corv);
-    // if corv == utils::LoginType::Customer.value() {
+    if corv == utils::LoginType::Vendor.value() {
+        vendor::verify_login(address, aid, cvid, signature).await
+    } else if corv == utils::LoginType::Mediator.value() {
+        mediator::verify_login(address, aid, cvid, signature).await
+    } else {
         customer::verify_login(address, aid, cvid, signature).await
-    // } // else {
-        //vendor::verify_login(address, signature).await
-    // }
+    }
 }
 
 /// Create authorization data to sign and expiration
@@ -87,16 +89,27 @@ pub async fn verify_access(address: &String, signature: &String) -> bool {
     }
     // verify signature on the data if not expired
     let data = f_auth.rnd;
-    let sig_address: String =
-        monero::verify_signature(String::from(address), data, String::from(signature)).await;
-    if sig_address == utils::ApplicationErrors::LoginError.value() {
-        debug!("signing failed");
-        return false;
+    match monero::verify_signature(String::from(address), data, String::from(signature)).await {
+        Ok(true) => (),
+        Ok(false) => {
+            debug!("signing failed");
+            return false;
+        }
+        Err(e) => {
+            error!("can't verify signature for {}: {}", address, e);
+            return false;
+        }
     }
     info!("auth verified");
     return true;
 }
 
+/// Check an address against the admin command line configuration
+pub fn is_admin(address: &String) -> bool {
+    let args = args::Args::parse();
+    args.admin_address != utils::empty_string() && &args.admin_address == address
+}
+
 /// get the auth expiration command line configuration
 fn get_auth_expiration() -> i64 {
     let args = args::Args::parse();
@@ -118,9 +131,16 @@ fn create_token(address: String, created: i64) -> String {
     String::from(token.expect("expected token").as_str())
 }
 
+/// Holds the xmr address from the verified token claims
 #[derive(Debug)]
 pub struct BearerToken(String);
 
+impl BearerToken {
+    pub fn get_address(&self) -> String {
+        String::from(&self.0)
+    }
+}
+
 #[derive(Debug)]
 pub enum BearerTokenError {
     Expired,
@@ -171,7 +191,7 @@ impl<'r> FromRequest<'r> for BearerToken {
                                 BearerTokenError::Expired,
                             ));
                         }
-                        Outcome::Success(BearerToken(String::from(token)))
+                        Outcome::Success(BearerToken(String::from(&claims["address"])))
                     }
                     Err(_) => Outcome::Failure((Status::Unauthorized, BearerTokenError::Invalid)),
                 }
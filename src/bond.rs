@@ -0,0 +1,226 @@
+// Vendor bond repo/service layer
+use crate::{args, db, models::*, monero, rpc, utils, vendor, wallet};
+use clap::Parser;
+use log::{debug, error, info};
+
+#[derive(Debug, PartialEq)]
+pub enum BondStatus {
+    Unpaid,
+    Paid,
+    Returned,
+    Forfeited,
+}
+
+impl BondStatus {
+    pub fn value(&self) -> String {
+        match *self {
+            BondStatus::Unpaid => String::from("Unpaid"),
+            BondStatus::Paid => String::from("Paid"),
+            BondStatus::Returned => String::from("Returned"),
+            BondStatus::Forfeited => String::from("Forfeited"),
+        }
+    }
+}
+
+/// Admin or mediator decision on a paid bond
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Return,
+    Forfeit,
+}
+
+impl Decision {
+    pub fn value(&self) -> String {
+        match *self {
+            Decision::Return => String::from("return"),
+            Decision::Forfeit => String::from("forfeit"),
+        }
+    }
+}
+
+/// Get the bond amount command line configuration
+pub fn get_amount() -> u128 {
+    let args = args::Args::parse();
+    args.vendor_bond
+}
+
+/// Bonds are only enforced when the operator sets an amount
+pub fn is_required() -> bool {
+    get_amount() > 0
+}
+
+/// A returned or forfeited bond is replaced when the vendor posts a new one
+pub fn is_resolved(b: &Bond) -> bool {
+    b.status == BondStatus::Returned.value() || b.status == BondStatus::Forfeited.value()
+}
+
+/// Status after a payment check, only an unpaid bond can become paid
+pub fn get_paid_status(b: &Bond, paid: u128) -> String {
+    if b.status == BondStatus::Unpaid.value() && paid >= b.amount {
+        BondStatus::Paid.value()
+    } else {
+        String::from(&b.status)
+    }
+}
+
+/// Vendors can be activated once a required bond is paid
+pub fn is_bonded(required: bool, b: &Bond) -> bool {
+    !required || b.status == BondStatus::Paid.value()
+}
+
+fn get_bid(vid: &String) -> String {
+    format!("bond{}", vid)
+}
+
+fn write(b: &Bond) {
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &b.bid);
+    db::Interface::write(&s.env, &s.handle, &b.bid, &Bond::to_db(b));
+}
+
+/// Bond lookup by vendor
+pub fn find(vid: &String) -> Bond {
+    let bid = get_bid(vid);
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &bid);
+    if r == utils::empty_string() {
+        return Default::default()
+    }
+    Bond::from_db(bid, r)
+}
+
+/// Return the vendor bond, creating the payment subaddress on first request
+/// or after the previous bond was resolved
+pub async fn find_or_create(vid: &String) -> Bond {
+    let f_bond: Bond = find(vid);
+    if f_bond.bid != utils::empty_string() && !is_resolved(&f_bond) {
+        return f_bond;
+    }
+    if is_resolved(&f_bond) {
+        info!("replacing {} bond for vendor {}", f_bond.status.to_lowercase(), vid);
+    }
+    info!("creating bond for vendor {}", vid);
+    let subaddress = match wallet::with(&utils::get_market_wallet(), monero::create_address(String::from(vid))).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("error creating bond subaddress: {}", e);
+            return Default::default();
+        }
+    };
+    let new_bond = Bond {
+        bid: get_bid(vid),
+        vid: String::from(vid),
+        amount: get_amount(),
+        address: subaddress.result.address,
+        address_index: subaddress.result.address_index,
+        created: chrono::offset::Utc::now().timestamp(),
+        status: BondStatus::Unpaid.value(),
+        tx_hash: utils::empty_string(),
+    };
+    debug!("insert bond: {:?}", &new_bond);
+    write(&new_bond);
+    new_bond
+}
+
+/// Check the bond subaddress unlocked balance and mark the bond as paid
+pub async fn check_payment(vid: &String) -> Bond {
+    let f_bond: Bond = find_or_create(vid).await;
+    if f_bond.status != BondStatus::Unpaid.value() {
+        return f_bond;
+    }
+    let balance = match wallet::with(&utils::get_market_wallet(), monero::get_balance(vec![f_bond.address_index])).await {
+        Ok(b) => b,
+        Err(e) => {
+            error!("error checking bond {}: {}", &f_bond.bid, e);
+            return f_bond;
+        }
+    };
+    let paid: u128 = balance.result.per_subaddress
+        .iter()
+        .filter(|s| s.address_index == f_bond.address_index)
+        .map(|s| s.unlocked_balance)
+        .sum();
+    debug!("bond {} paid {} of {}", &f_bond.bid, paid, f_bond.amount);
+    let status = get_paid_status(&f_bond, paid);
+    if status == f_bond.status {
+        return f_bond;
+    }
+    info!("bond paid for vendor {}", vid);
+    let u_bond = Bond::update_status(f_bond, status, utils::empty_string());
+    write(&u_bond);
+    u_bond
+}
+
+/// Return or forfeit a paid bond. Either way the vendor is deactivated.
+pub async fn resolve(vid: &String, decision: String) -> Bond {
+    let f_bond: Bond = find(vid);
+    if f_bond.status != BondStatus::Paid.value() {
+        error!("bond for vendor {} is not resolvable", vid);
+        return f_bond;
+    }
+    let u_bond = if decision == Decision::Return.value() {
+        let f_vend: Vendor = vendor::find(vid);
+        let address = String::from(&f_vend.v_xmr_address);
+        let sweep = match wallet::with(&utils::get_market_wallet(), monero::sweep_all(address, vec![f_bond.address_index])).await {
+            Ok(s) => s,
+            Err(e) if e.wallet_error() == Some(rpc::WalletError::NotEnoughMoney) => {
+                error!("bond for vendor {} has no unlocked balance to return yet", vid);
+                return f_bond;
+            }
+            Err(e) => {
+                error!("error returning bond for vendor {}: {}", vid, e);
+                return f_bond;
+            }
+        };
+        info!("returned bond for vendor {}", vid);
+        let tx_hash = sweep.result.tx_hash_list.join(",");
+        Bond::update_status(f_bond, BondStatus::Returned.value(), tx_hash)
+    } else if decision == Decision::Forfeit.value() {
+        info!("forfeited bond for vendor {}", vid);
+        Bond::update_status(f_bond, BondStatus::Forfeited.value(), utils::empty_string())
+    } else {
+        error!("invalid bond decision: {}", decision);
+        return f_bond;
+    };
+    write(&u_bond);
+    vendor::set_active(vendor::find(vid), false);
+    u_bond
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bond(status: BondStatus) -> Bond {
+        Bond { amount: 100, status: status.value(), ..Default::default() }
+    }
+
+    #[test]
+    fn get_paid_status_test() {
+        assert_eq!(get_paid_status(&bond(BondStatus::Unpaid), 99), BondStatus::Unpaid.value());
+        assert_eq!(get_paid_status(&bond(BondStatus::Unpaid), 100), BondStatus::Paid.value());
+        assert_eq!(get_paid_status(&bond(BondStatus::Paid), 100), BondStatus::Paid.value());
+        // a resolved bond doesn't become paid again, the vendor posts a new one
+        assert_eq!(get_paid_status(&bond(BondStatus::Returned), 100), BondStatus::Returned.value());
+        assert_eq!(get_paid_status(&bond(BondStatus::Forfeited), 100), BondStatus::Forfeited.value());
+    }
+
+    #[test]
+    fn is_resolved_test() {
+        assert!(!is_resolved(&Default::default()));
+        assert!(!is_resolved(&bond(BondStatus::Unpaid)));
+        assert!(!is_resolved(&bond(BondStatus::Paid)));
+        assert!(is_resolved(&bond(BondStatus::Returned)));
+        assert!(is_resolved(&bond(BondStatus::Forfeited)));
+    }
+
+    #[test]
+    fn is_bonded_test() {
+        assert!(is_bonded(false, &Default::default()));
+        assert!(is_bonded(false, &bond(BondStatus::Forfeited)));
+        assert!(is_bonded(true, &bond(BondStatus::Paid)));
+        assert!(!is_bonded(true, &bond(BondStatus::Unpaid)));
+        assert!(!is_bonded(true, &bond(BondStatus::Returned)));
+        assert!(!is_bonded(true, &Default::default()));
+    }
+}
@@ -0,0 +1,238 @@
+// Market commission, taken out of every release as an extra destination
+use crate::{args, auth, db, models::*, monero, order, search, utils, wallet};
+use clap::Parser;
+use log::{debug, error, info};
+
+/// Percentages are in basis points, 10000 is the whole order
+const MAX_BASIS_POINTS: i64 = 10000;
+/// Share of the order, in basis points, the commission always leaves the vendor
+const MIN_VENDOR_SHARE: i64 = 100;
+
+/// How the commission is charged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommissionType {
+    Percent,
+    Flat,
+}
+
+impl CommissionType {
+    pub fn value(&self) -> String {
+        match *self {
+            CommissionType::Percent => String::from("percent"),
+            CommissionType::Flat => String::from("flat"),
+        }
+    }
+    pub fn from_value(v: &str) -> Option<CommissionType> {
+        match v {
+            "percent" => Some(CommissionType::Percent),
+            "flat" => Some(CommissionType::Flat),
+            _ => None,
+        }
+    }
+}
+
+/// Get the default commission command line configuration
+fn get_default() -> Commission {
+    let args = args::Args::parse();
+    Commission {
+        category: utils::empty_string(),
+        kind: args.commission_type,
+        value: args.commission,
+    }
+}
+
+fn get_key(category: &str) -> String {
+    format!("cm{}", category)
+}
+
+/// Key for the list of categories with their own commission
+fn get_list_key() -> String {
+    String::from("cml")
+}
+
+/// Key for the list of orids with a commission paid out in a release
+fn get_paid_key() -> String {
+    String::from("cmpaid")
+}
+
+fn is_valid(c: &Commission) -> bool {
+    match CommissionType::from_value(&c.kind) {
+        Some(CommissionType::Percent) => (0..=MAX_BASIS_POINTS - MIN_VENDOR_SHARE).contains(&c.value),
+        Some(CommissionType::Flat) => c.value >= 0,
+        None => false,
+    }
+}
+
+/// Commission on an order amount, never more than leaves the vendor its
+/// minimum share
+pub fn get_fee(c: &Commission, amount: i64) -> i64 {
+    let amount = amount.max(0);
+    let of_amount = |bps: i64| (amount as i128 * bps as i128 / MAX_BASIS_POINTS as i128) as i64;
+    let fee = match CommissionType::from_value(&c.kind) {
+        Some(CommissionType::Percent) => of_amount(c.value),
+        Some(CommissionType::Flat) => c.value,
+        None => 0,
+    };
+    fee.clamp(0, of_amount(MAX_BASIS_POINTS - MIN_VENDOR_SHARE))
+}
+
+/// Commission for a category, the default unless the admin set one
+pub fn find(category: &str) -> Commission {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_key(category));
+    if category.is_empty() || r == utils::empty_string() {
+        return get_default();
+    }
+    Commission::from_db(String::from(category), r)
+}
+
+/// The market wallet is needed once any commission can be charged
+pub fn is_enabled() -> bool {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key());
+    get_default().value > 0 || !split_list(r).is_empty()
+}
+
+/// Default commission followed by the category overrides, admin only
+pub fn find_all(address: &String) -> Vec<Commission> {
+    if !auth::is_admin(address) {
+        error!("{} can't view commissions", address);
+        return Vec::new();
+    }
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key());
+    let mut commissions = vec![get_default()];
+    commissions.extend(split_list(r).iter().map(|c| find(c)));
+    commissions
+}
+
+/// Set the commission for a category, admin only. Existing orders keep
+/// the fee they were created with.
+pub fn set(address: &String, category: &str, kind: String, value: i64) -> Commission {
+    let category = search::normalize(category);
+    let new_commission = Commission { category: String::from(&category), kind, value };
+    if !auth::is_admin(address) || category.is_empty() || !is_valid(&new_commission) {
+        error!("invalid commission for category {}", &category);
+        return Default::default();
+    }
+    info!("setting commission for category {}", &category);
+    debug!("commission: {:?}", &new_commission);
+    let s = db::Interface::open();
+    let key = get_key(&category);
+    if db::Interface::read(&s.env, &s.handle, &key) == utils::empty_string() {
+        db::Interface::append(&s.env, &s.handle, &get_list_key(), &category);
+    }
+    db::Interface::delete(&s.env, &s.handle, &key);
+    db::Interface::write(&s.env, &s.handle, &key, &Commission::to_db(&new_commission));
+    new_commission
+}
+
+/// Drop a category commission so the default applies again, admin only
+pub fn remove(address: &String, category: &str) -> bool {
+    let category = search::normalize(category);
+    let s = db::Interface::open();
+    let key = get_key(&category);
+    if !auth::is_admin(address) || db::Interface::read(&s.env, &s.handle, &key) == utils::empty_string() {
+        return false;
+    }
+    info!("removing commission for category {}", &category);
+    db::Interface::delete(&s.env, &s.handle, &key);
+    db::Interface::remove_from_list(&s.env, &s.handle, &get_list_key(), &category);
+    true
+}
+
+/// Create the market wallet subaddress an order's commission is paid to
+pub async fn create_address(orid: &String) -> String {
+    match wallet::with(&utils::get_market_wallet(), monero::create_address(String::from(orid))).await {
+        Ok(s) => s.result.address,
+        Err(e) => {
+            error!("error creating commission address for order {}: {}", orid, e);
+            utils::empty_string()
+        }
+    }
+}
+
+/// Note a submitted release or split that paid the commission
+pub fn record(o: &Order) {
+    debug!("commission of {} paid on order {}", o.o_fee, &o.orid);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_paid_key(), &o.orid);
+}
+
+/// Orders released or settled within [from, to) and the commission they paid
+pub fn get_report(orders: Vec<Order>, from: i64, to: i64) -> (Vec<Order>, i64) {
+    let paid: Vec<Order> = orders
+        .into_iter()
+        .filter(|o| {
+            let released = order::get_status_date(o, order::OrderStatus::Released)
+                .max(order::get_status_date(o, order::OrderStatus::Settled));
+            released >= from && released < to
+        })
+        .collect();
+    let total = paid.iter().map(|o| o.o_fee).sum();
+    (paid, total)
+}
+
+/// Commission collected in a period, admin only
+pub fn report(address: &String, from: i64, to: i64) -> (Vec<Order>, i64) {
+    if !auth::is_admin(address) {
+        error!("{} can't view the commission report", address);
+        return (Vec::new(), 0);
+    }
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_paid_key());
+    let orders: Vec<Order> = split_list(r)
+        .iter()
+        .map(order::find)
+        .filter(|o| o.orid != utils::empty_string())
+        .collect();
+    get_report(orders, from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commission(kind: CommissionType, value: i64) -> Commission {
+        Commission { kind: kind.value(), value, ..Default::default() }
+    }
+
+    #[test]
+    fn get_fee_test() {
+        assert_eq!(get_fee(&commission(CommissionType::Percent, 250), 1_000_000), 25_000);
+        assert_eq!(get_fee(&commission(CommissionType::Percent, 0), 1_000_000), 0);
+        assert_eq!(get_fee(&commission(CommissionType::Flat, 5000), 1_000_000), 5000);
+        assert_eq!(get_fee(&commission(CommissionType::Flat, 5000), 1000), 990);
+        assert_eq!(get_fee(&commission(CommissionType::Percent, MAX_BASIS_POINTS), 1_000_000), 990_000);
+        assert_eq!(get_fee(&commission(CommissionType::Flat, -5), 1000), 0);
+        assert_eq!(get_fee(&Commission::default(), 1_000_000), 0);
+    }
+
+    #[test]
+    fn is_valid_test() {
+        assert!(is_valid(&commission(CommissionType::Percent, MAX_BASIS_POINTS - MIN_VENDOR_SHARE)));
+        assert!(!is_valid(&commission(CommissionType::Percent, MAX_BASIS_POINTS)));
+        assert!(!is_valid(&commission(CommissionType::Flat, -1)));
+        assert!(!is_valid(&Commission { kind: String::from("other"), ..Default::default() }));
+    }
+
+    #[test]
+    fn get_report_test() {
+        let paid_out = |orid: &str, status: order::OrderStatus, timestamp: i64, o_fee: i64| Order {
+            orid: String::from(orid),
+            o_fee,
+            o_history: vec![StatusChange { status: status.value(), role: order::Role::System.value(), timestamp }],
+            ..Default::default()
+        };
+        let released = order::OrderStatus::Released;
+        let orders = vec![
+            paid_out("o1", released, 100, 10),
+            paid_out("o2", released, 200, 20),
+            paid_out("o3", released, 300, 30),
+            paid_out("o4", order::OrderStatus::Settled, 250, 5),
+        ];
+        let (paid, total) = get_report(orders, 100, 300);
+        assert_eq!(paid.len(), 3);
+        assert_eq!(total, 35);
+    }
+}
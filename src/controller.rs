@@ -1,12 +1,26 @@
-use rocket::http::Status;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::{ContentType, Status};
 use rocket::response::status::Custom;
 use rocket::serde::json::Json;
-use rocket::{get};
+use rocket::{delete, get, patch, post};
 
+use crate::bond;
+use crate::commission;
 use crate::customer;
+use crate::dispute;
+use crate::evidence;
+use crate::feedback;
+use crate::media;
+use crate::mediator;
 use crate::models;
 use crate::monero;
+use crate::order;
+use crate::product;
 use crate::reqres;
+use crate::search;
+use crate::shipping;
+use crate::utils;
+use crate::vendor;
 use crate::{auth};
 
 // JSON APIs
@@ -15,7 +29,10 @@ use crate::{auth};
 /// Protected: false
 #[get("/version")]
 pub async fn get_version() -> Custom<Json<reqres::XmrRpcVersionResponse>> {
-    Custom(Status::Ok, Json(monero::get_version().await))
+    match monero::get_version().await {
+        Ok(version) => Custom(Status::Ok, Json(version)),
+        Err(_) => Custom(Status::ServiceUnavailable, Json(Default::default())),
+    }
 }
 
 /// Return a single customer's information
@@ -33,16 +50,136 @@ pub async fn get_customer(
     )
 }
 
-// /// Get a single vendor's information
-// /// Protected: true
-// #[get("/<address>")]
-// pub async fn get_vendor(
-//     address: String,
-//     _token: auth::BearerToken,
-// ) -> Custom<Json<reqres::GetVendorResponse>> {
-//     let m_vendor: models::Vendor = vendor::find(address).await;
-//     Custom(Status::Ok, Json(reqres::GetVendorResponse::build(m_vendor)))
-// }
+/// Get a single vendor's information
+/// Protected: true
+#[get("/<_address>/<vid>")]
+pub async fn get_vendor(
+    _address: String,
+    vid: String,
+    _token: auth::BearerToken,
+) -> Custom<Json<reqres::GetVendorResponse>> {
+    let m_vendor: models::Vendor = vendor::find(&vid);
+    Custom(Status::Ok, Json(reqres::GetVendorResponse::build(m_vendor)))
+}
+
+/// Public vendor profile with reputation statistics
+/// Protected: false
+#[get("/<vid>/profile")]
+pub async fn get_vendor_profile(vid: String) -> Custom<Json<reqres::GetVendorProfileResponse>> {
+    let m_vendor: models::Vendor = vendor::find(&vid);
+    if m_vendor.vid == utils::empty_string() {
+        return Custom(Status::NotFound, Json(Default::default()));
+    }
+    let stats = vendor::stats(&m_vendor);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetVendorProfileResponse::build(m_vendor, stats)),
+    )
+}
+
+/// Get the vendor bond and the subaddress to pay it to
+/// Protected: true
+#[get("/<address>/<vid>/bond")]
+pub async fn get_vendor_bond(
+    address: String,
+    vid: String,
+    _token: auth::BearerToken,
+) -> Custom<Json<reqres::GetBondResponse>> {
+    let m_vendor: models::Vendor = vendor::find(&vid);
+    if m_vendor.v_xmr_address != address {
+        return Custom(Status::Unauthorized, Json(Default::default()));
+    }
+    let m_bond: models::Bond = bond::check_payment(&vid).await;
+    Custom(Status::Ok, Json(reqres::GetBondResponse::build(m_bond)))
+}
+
+/// Activate a vendor, the bond must be paid if one is configured
+/// Protected: true
+#[patch("/<address>/<vid>/activate")]
+pub async fn activate_vendor(
+    address: String,
+    vid: String,
+    _token: auth::BearerToken,
+) -> Custom<Json<reqres::GetVendorResponse>> {
+    let f_vendor: models::Vendor = vendor::find(&vid);
+    if f_vendor.v_xmr_address != address {
+        return Custom(Status::Unauthorized, Json(Default::default()));
+    }
+    let m_vendor: models::Vendor = vendor::activate(&vid).await;
+    if !m_vendor.active {
+        return Custom(
+            Status::PaymentRequired,
+            Json(reqres::GetVendorResponse::build(m_vendor)),
+        );
+    }
+    Custom(Status::Ok, Json(reqres::GetVendorResponse::build(m_vendor)))
+}
+
+/// Return or forfeit a vendor bond, admins and mediators only
+/// Protected: true
+#[patch("/<address>/<vid>/<decision>")]
+pub async fn resolve_bond(
+    address: String,
+    vid: String,
+    decision: String,
+    _token: auth::BearerToken,
+) -> Custom<Json<reqres::GetBondResponse>> {
+    if !auth::is_admin(&address) && !mediator::is_mediator(&address) {
+        return Custom(Status::Unauthorized, Json(Default::default()));
+    }
+    let m_bond: models::Bond = bond::resolve(&vid, decision).await;
+    Custom(Status::Ok, Json(reqres::GetBondResponse::build(m_bond)))
+}
+
+/// Leave feedback on a delivered order
+/// Protected: true
+#[post("/<address>/<oid>", data = "<r_feedback>")]
+pub async fn create_feedback(
+    address: String,
+    oid: String,
+    r_feedback: Json<reqres::FeedbackRequest>,
+    _token: auth::BearerToken,
+) -> Custom<Json<reqres::GetFeedbackResponse>> {
+    let m_feedback: models::Feedback =
+        feedback::create(&oid, &address, r_feedback.rating, String::from(&r_feedback.comment));
+    if m_feedback.fid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(Status::Created, Json(reqres::GetFeedbackResponse::build(m_feedback)))
+}
+
+/// Vendor reply to feedback
+/// Protected: true
+#[patch("/<address>/<oid>/reply", data = "<r_reply>")]
+pub async fn reply_feedback(
+    address: String,
+    oid: String,
+    r_reply: Json<reqres::FeedbackReplyRequest>,
+    _token: auth::BearerToken,
+) -> Custom<Json<reqres::GetFeedbackResponse>> {
+    let m_feedback: models::Feedback =
+        feedback::reply(&oid, &address, String::from(&r_reply.reply));
+    if m_feedback.fid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(Status::Ok, Json(reqres::GetFeedbackResponse::build(m_feedback)))
+}
+
+/// List feedback for a vendor
+/// Protected: false
+#[get("/vendor/<vid>")]
+pub async fn get_vendor_feedback(vid: String) -> Custom<Json<reqres::GetFeedbackListResponse>> {
+    let m_feedback: Vec<models::Feedback> = feedback::find_all(&vid);
+    Custom(Status::Ok, Json(reqres::GetFeedbackListResponse::build(m_feedback)))
+}
+
+/// List feedback for a product
+/// Protected: false
+#[get("/product/<pid>")]
+pub async fn get_product_feedback(pid: String) -> Custom<Json<reqres::GetFeedbackListResponse>> {
+    let m_feedback: Vec<models::Feedback> = feedback::find_all(&pid);
+    Custom(Status::Ok, Json(reqres::GetFeedbackListResponse::build(m_feedback)))
+}
 
 /// Login with wallet signature
 #[get("/login/<corv>/<address>/<signature>/<aid>/<cvid>")]
@@ -90,123 +227,668 @@ pub async fn login(
 //     Custom(Status::Ok, Json(reqres::GetVendorResponse::build(m_vendor)))
 // }
 
-// /// Create a product by passing vendor address
-// #[get("/<address>/create")]
-// pub async fn create_product(
-//     address: String,
-//     _token: auth::BearerToken,
-// ) -> Custom<Json<reqres::GetProductResponse>> {
-//     let v: models::Vendor = vendor::find(address).await;
-//     let m_product: models::Product = product::create(v.vid).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetProductResponse::build(m_product)),
-//     )
-// }
+/// Create a product for the vendor
+/// Protected: true
+#[post("/<_address>/<vid>", data = "<r_product>")]
+pub async fn create_product(
+    _address: String,
+    vid: String,
+    r_product: Json<reqres::ProductRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product = product::create(&vid, &token.get_address(), &r_product);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::Unauthorized, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
 
-// /// Get all products by passing vendor address
-// #[get("/<address>")]
-// pub async fn get_vendor_products(
-//     address: String,
-//     _token: auth::BearerToken,
-// ) -> Custom<Json<reqres::GetVendorProductsResponse>> {
-//     let m_vendor: models::Vendor = vendor::find(address).await;
-//     let m_products: Vec<models::Product> = product::find_all(m_vendor.vid).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetVendorProductsResponse::build(m_products)),
-//     )
-// }
+/// Get a single product
+/// Protected: false
+#[get("/<pid>")]
+pub async fn get_product(pid: String) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product = product::find(&pid);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::NotFound, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
 
-// /// Update product information
-// #[patch("/<_address>/update/<pid>/<data>/<update_type>")]
-// pub async fn update_product(
-//     _address: String,
-//     pid: String,
-//     _token: auth::BearerToken,
-//     data: String,
-//     update_type: i32,
-// ) -> Custom<Json<reqres::GetProductResponse>> {
-//     let m_product: models::Product = product::modify(pid, data, update_type).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetProductResponse::build(m_product)),
-//     )
-// }
+/// Get all products for a vendor
+/// Protected: false
+#[get("/<vid>")]
+pub async fn get_vendor_products(vid: String) -> Custom<Json<reqres::GetVendorProductsResponse>> {
+    let m_products: Vec<models::Product> = product::find_all(&vid);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetVendorProductsResponse::build(m_products)),
+    )
+}
 
-// /// Initialize order
-// #[get("/<address>/create/<pid>")]
-// pub async fn initialize_order(
-//     address: String,
-//     _token: auth::BearerToken,
-//     pid: String,
-// ) -> Custom<Json<reqres::GetOrderResponse>> {
-//     // get the cid from the address after verification
-//     let m_customer = customer::find(address).await;
-//     let temp_pid = String::from(&pid);
-//     let m_order: models::Order = order::create(m_customer.cid, temp_pid).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetOrderResponse::build(pid, m_order)),
-//     )
-// }
+/// Keyword search and browse products with filters
+/// Protected: false
+#[allow(clippy::too_many_arguments)]
+#[get("/?<q>&<category>&<min_price>&<max_price>&<currency>&<vid>&<in_stock>&<page>&<per_page>")]
+pub async fn search_products(
+    q: Option<String>,
+    category: Option<String>,
+    min_price: Option<i64>,
+    max_price: Option<i64>,
+    currency: Option<String>,
+    vid: Option<String>,
+    in_stock: Option<bool>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+) -> Custom<Json<reqres::GetSearchResponse>> {
+    let query = search::Query {
+        keywords: q.unwrap_or_default(),
+        category,
+        min_price,
+        max_price,
+        currency: currency.unwrap_or_default(),
+        vid,
+        in_stock,
+        page: page.unwrap_or(0),
+        per_page: per_page.unwrap_or(20),
+    };
+    let (m_products, total) = search::search(&query);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetSearchResponse::build(m_products, query.page, query.per_page, total)),
+    )
+}
 
-// /// Update order information
-// #[patch("/<_address>/update/<pid>/<oid>/<data>/<update_type>")]
-// pub async fn update_order(
-//     _address: String,
-//     oid: String,
-//     pid: String,
-//     _token: auth::BearerToken,
-//     data: String,
-//     update_type: i32,
-// ) -> Custom<Json<reqres::GetOrderResponse>> {
-//     let temp_pid: String = String::from(&pid);
-//     let m_order: models::Order = order::modify(oid, pid, data, update_type).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetOrderResponse::build(temp_pid, m_order)),
-//     )
-// }
+/// Update product information
+/// Protected: true
+#[patch("/<_address>/<pid>", data = "<r_product>")]
+pub async fn update_product(
+    _address: String,
+    pid: String,
+    r_product: Json<reqres::ProductRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product = product::modify(&pid, &token.get_address(), &r_product);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::Unauthorized, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
 
-// /// Get all orders
-// ///  by passing auth
-// #[get("/<address>/<corv>")]
-// pub async fn get_orders(
-//     address: String,
-//     corv: String,
-//     _token: auth::BearerToken,
-// ) -> Custom<Json<reqres::GetOrdersResponse>> {
-//     let m_orders: Vec<models::Order> = order::find_all(address, corv).await;
-//     Custom(Status::Ok, Json(reqres::GetOrdersResponse::build(m_orders)))
-// }
+/// Upload a product image (jpeg or png), metadata is stripped
+/// Protected: true
+#[post("/<_address>/<pid>/image", data = "<data>")]
+pub async fn upload_product_image(
+    _address: String,
+    pid: String,
+    data: Data<'_>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let bytes = match data.open(5.mebibytes()).into_bytes().await {
+        Ok(b) if b.is_complete() => b.into_inner(),
+        _ => return Custom(Status::PayloadTooLarge, Json(Default::default())),
+    };
+    let m_product: models::Product = product::add_image(&pid, &token.get_address(), &bytes);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
 
-// /// Create a dispute
-// #[get("/<_address>/create/<oid>/<txset>")]
-// pub async fn create_dispute(
-//     _address: String,
-//     _token: auth::BearerToken,
-//     oid: String,
-//     txset: String,
-// ) -> Custom<Json<reqres::GetDisputeResponse>> {
-//     let m_dispute: models::Dispute = dispute::create(oid, txset).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetDisputeResponse::build(m_dispute)),
-//     )
-// }
+/// Remove a product image
+/// Protected: true
+#[delete("/<_address>/<pid>/image/<hash>")]
+pub async fn delete_product_image(
+    _address: String,
+    pid: String,
+    hash: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product = product::remove_image(&pid, &token.get_address(), &hash);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
 
-// /// Create a dispute
-// #[get("/<_address>/<oid>")]
-// pub async fn get_dispute(
-//     _address: String,
-//     _token: auth::BearerToken,
-//     oid: String,
-// ) -> Custom<Json<reqres::GetDisputeResponse>> {
-//     let m_dispute: models::Dispute = dispute::find(oid).await;
-//     Custom(
-//         Status::Ok,
-//         Json(reqres::GetDisputeResponse::build(m_dispute)),
-//     )
-// }
+/// Serve a product image by content hash
+/// Protected: false
+#[get("/<hash>")]
+pub async fn get_image(hash: String) -> Result<(ContentType, Vec<u8>), Status> {
+    let image = media::load(&hash);
+    if image.is_empty() {
+        return Err(Status::NotFound);
+    }
+    Ok((ContentType::JPEG, image))
+}
+
+/// Delete a product
+/// Protected: true
+#[delete("/<_address>/<pid>")]
+pub async fn delete_product(
+    _address: String,
+    pid: String,
+    token: auth::BearerToken,
+) -> Status {
+    if !product::delete(&pid, &token.get_address()) {
+        return Status::Unauthorized;
+    }
+    Status::NoContent
+}
+
+/// Add a product variant with its own price and stock
+/// Protected: true
+#[post("/<_address>/<pid>/variant", data = "<r_variant>")]
+pub async fn create_variant(
+    _address: String,
+    pid: String,
+    r_variant: Json<reqres::VariantRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product = product::add_variant(&pid, &token.get_address(), &r_variant);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
+
+/// Update a product variant
+/// Protected: true
+#[patch("/<_address>/<pid>/variant/<vrid>", data = "<r_variant>")]
+pub async fn update_variant(
+    _address: String,
+    pid: String,
+    vrid: String,
+    r_variant: Json<reqres::VariantRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product =
+        product::modify_variant(&pid, &token.get_address(), &vrid, &r_variant);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
+
+/// Remove a product variant
+/// Protected: true
+#[delete("/<_address>/<pid>/variant/<vrid>")]
+pub async fn delete_variant(
+    _address: String,
+    pid: String,
+    vrid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetProductResponse>> {
+    let m_product: models::Product = product::remove_variant(&pid, &token.get_address(), &vrid);
+    if m_product.pid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetProductResponse::build(m_product)),
+    )
+}
+
+/// Create a vendor shipping option
+/// Protected: true
+#[post("/<_address>/<vid>", data = "<r_shipping>")]
+pub async fn create_shipping(
+    _address: String,
+    vid: String,
+    r_shipping: Json<reqres::ShippingRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetShippingResponse>> {
+    let m_shipping: models::Shipping = shipping::create(&vid, &token.get_address(), &r_shipping);
+    if m_shipping.sid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetShippingResponse::build(m_shipping)),
+    )
+}
+
+/// List shipping options offered by a vendor
+/// Protected: false
+#[get("/<vid>")]
+pub async fn get_vendor_shipping(vid: String) -> Custom<Json<reqres::GetShippingListResponse>> {
+    let m_shipping: Vec<models::Shipping> = shipping::find_all(&vid);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetShippingListResponse::build(m_shipping)),
+    )
+}
+
+/// Update a shipping option
+/// Protected: true
+#[patch("/<_address>/<sid>", data = "<r_shipping>")]
+pub async fn update_shipping(
+    _address: String,
+    sid: String,
+    r_shipping: Json<reqres::ShippingRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetShippingResponse>> {
+    let m_shipping: models::Shipping = shipping::modify(&sid, &token.get_address(), &r_shipping);
+    if m_shipping.sid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetShippingResponse::build(m_shipping)),
+    )
+}
+
+/// Delete a shipping option
+/// Protected: true
+#[delete("/<_address>/<sid>")]
+pub async fn delete_shipping(
+    _address: String,
+    sid: String,
+    token: auth::BearerToken,
+) -> Status {
+    if !shipping::delete(&sid, &token.get_address()) {
+        return Status::Unauthorized;
+    }
+    Status::NoContent
+}
+
+/// Default commission and category overrides, admin only
+/// Protected: true
+#[get("/<_address>")]
+pub async fn get_commissions(
+    _address: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetCommissionsResponse>> {
+    let m_commissions: Vec<models::Commission> = commission::find_all(&token.get_address());
+    Custom(Status::Ok, Json(reqres::GetCommissionsResponse::build(m_commissions)))
+}
+
+/// Set the commission for a category, admin only
+/// Protected: true
+#[patch("/<_address>/<category>", data = "<r_commission>")]
+pub async fn set_commission(
+    _address: String,
+    category: String,
+    r_commission: Json<reqres::CommissionRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetCommissionResponse>> {
+    let kind = String::from(&r_commission.kind);
+    let m_commission: models::Commission =
+        commission::set(&token.get_address(), &category, kind, r_commission.value);
+    if m_commission.category == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetCommissionResponse::build(m_commission)),
+    )
+}
+
+/// Remove a category commission so the default applies, admin only
+/// Protected: true
+#[delete("/<_address>/<category>")]
+pub async fn delete_commission(
+    _address: String,
+    category: String,
+    token: auth::BearerToken,
+) -> Status {
+    if !commission::remove(&token.get_address(), &category) {
+        return Status::BadRequest;
+    }
+    Status::NoContent
+}
+
+/// Commission collected from releases in a period, admin only
+/// Protected: true
+#[get("/<_address>/report?<from>&<to>")]
+pub async fn get_commission_report(
+    _address: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetCommissionReportResponse>> {
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or_else(|| chrono::offset::Utc::now().timestamp() + 1);
+    let (m_orders, total) = commission::report(&token.get_address(), from, to);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetCommissionReportResponse::build(from, to, m_orders, total)),
+    )
+}
+
+/// Create an order, the multisig wallet is created with it
+/// Protected: true
+#[post("/<_address>/<cid>/<pid>", data = "<r_order>")]
+pub async fn create_order(
+    _address: String,
+    cid: String,
+    pid: String,
+    r_order: Json<reqres::OrderRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetOrderResponse>> {
+    let m_order: models::Order = order::create(&cid, &token.get_address(), &pid, &r_order).await;
+    if m_order.orid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetOrderResponse::build(pid, m_order)),
+    )
+}
+
+/// Get a single order, customer vendor and mediator only
+/// Protected: true
+#[get("/<_address>/<oid>")]
+pub async fn get_order(
+    _address: String,
+    oid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetOrderResponse>> {
+    let m_order: models::Order = order::find_for(&oid, &token.get_address());
+    if m_order.orid == utils::empty_string() {
+        return Custom(Status::NotFound, Json(Default::default()));
+    }
+    let pid = String::from(&m_order.p_id);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetOrderResponse::build(pid, m_order)),
+    )
+}
+
+/// Update order information
+/// Protected: true
+#[patch("/<_address>/<oid>", data = "<r_update>")]
+pub async fn update_order(
+    _address: String,
+    oid: String,
+    r_update: Json<reqres::OrderUpdateRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetOrderResponse>> {
+    let update_type = match order::UpdateType::from_value(r_update.update_type) {
+        Some(u) => u,
+        None => return Custom(Status::BadRequest, Json(Default::default())),
+    };
+    let data = String::from(&r_update.data);
+    let m_order: models::Order = order::modify(&oid, &token.get_address(), update_type, data).await;
+    if m_order.orid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    let pid = String::from(&m_order.p_id);
+    Custom(
+        Status::Ok,
+        Json(reqres::GetOrderResponse::build(pid, m_order)),
+    )
+}
+
+/// Get all orders for a customer or vendor
+/// Protected: true
+#[get("/<_address>/<cvid>")]
+pub async fn get_orders(
+    _address: String,
+    cvid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetOrdersResponse>> {
+    let m_orders: Vec<models::Order> = order::find_all_for(&cvid, &token.get_address());
+    Custom(Status::Ok, Json(reqres::GetOrdersResponse::build(m_orders)))
+}
+
+/// Open a dispute on a shipped order, customer or vendor only
+/// Protected: true
+#[post("/<_address>/<oid>", data = "<r_dispute>")]
+pub async fn create_dispute(
+    _address: String,
+    oid: String,
+    r_dispute: Json<reqres::DisputeRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetDisputeResponse>> {
+    let reason = String::from(&r_dispute.reason);
+    let m_dispute: models::Dispute = dispute::create(&oid, &token.get_address(), reason);
+    if m_dispute.did == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetDisputeResponse::build(m_dispute)),
+    )
+}
+
+/// Get a dispute, customer vendor and assigned mediator only
+/// Protected: true
+#[get("/<_address>/<oid>")]
+pub async fn get_dispute(
+    _address: String,
+    oid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetDisputeResponse>> {
+    let m_dispute: models::Dispute = dispute::find_for(&oid, &token.get_address());
+    if m_dispute.did == utils::empty_string() {
+        return Custom(Status::NotFound, Json(Default::default()));
+    }
+    let mediator_pgp = mediator::find(&m_dispute.m_id).m_pgp;
+    Custom(
+        Status::Ok,
+        Json(reqres::GetDisputeResponse { mediator_pgp, ..reqres::GetDisputeResponse::build(m_dispute) }),
+    )
+}
+
+/// Add a pgp encrypted statement to an assigned dispute
+/// Protected: true
+#[post("/<_address>/<oid>/statement", data = "<r_statement>")]
+pub async fn add_dispute_statement(
+    _address: String,
+    oid: String,
+    r_statement: Json<reqres::StatementRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetEvidenceResponse>> {
+    let statement = String::from(&r_statement.statement);
+    let m_evidence: models::Evidence = evidence::add_statement(&oid, &token.get_address(), statement);
+    if m_evidence.eid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetEvidenceResponse::build(m_evidence)),
+    )
+}
+
+/// Upload a pgp encrypted evidence file to an assigned dispute
+/// Protected: true
+#[post("/<_address>/<oid>/evidence", data = "<data>")]
+pub async fn upload_dispute_evidence(
+    _address: String,
+    oid: String,
+    data: Data<'_>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetEvidenceResponse>> {
+    let bytes = match data.open(5.mebibytes()).into_bytes().await {
+        Ok(b) if b.is_complete() => b.into_inner(),
+        _ => return Custom(Status::PayloadTooLarge, Json(Default::default())),
+    };
+    let m_evidence: models::Evidence = evidence::add_file(&oid, &token.get_address(), &bytes);
+    if m_evidence.eid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetEvidenceResponse::build(m_evidence)),
+    )
+}
+
+/// Download an encrypted evidence file, customer vendor and assigned mediator only
+/// Protected: true
+#[get("/<_address>/<oid>/evidence/<eid>")]
+pub async fn get_dispute_evidence(
+    _address: String,
+    oid: String,
+    eid: String,
+    token: auth::BearerToken,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    let file = evidence::load_file(&oid, &eid, &token.get_address());
+    if file.is_empty() {
+        return Err(Status::NotFound);
+    }
+    Ok((ContentType::Binary, file))
+}
+
+/// Status changes, dispute events and evidence in order
+/// Protected: true
+#[get("/<_address>/<oid>/timeline")]
+pub async fn get_dispute_timeline(
+    _address: String,
+    oid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetTimelineResponse>> {
+    let events: Vec<evidence::TimelineEvent> = evidence::timeline(&oid, &token.get_address());
+    if events.is_empty() {
+        return Custom(Status::NotFound, Json(reqres::GetTimelineResponse::build(oid, events)));
+    }
+    Custom(Status::Ok, Json(reqres::GetTimelineResponse::build(oid, events)))
+}
+
+/// Disputes waiting for a mediator, mediators and admin only
+/// Protected: true
+#[get("/<_address>/queue")]
+pub async fn get_dispute_queue(
+    _address: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetDisputesResponse>> {
+    let m_disputes: Vec<models::Dispute> = dispute::find_queue(&token.get_address());
+    Custom(Status::Ok, Json(reqres::GetDisputesResponse::build(m_disputes)))
+}
+
+/// Disputes assigned to the calling mediator
+/// Protected: true
+#[get("/<_address>/assigned")]
+pub async fn get_assigned_disputes(
+    _address: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetDisputesResponse>> {
+    let m_disputes: Vec<models::Dispute> = dispute::find_assigned(&token.get_address());
+    Custom(Status::Ok, Json(reqres::GetDisputesResponse::build(m_disputes)))
+}
+
+/// Assign a dispute to a mediator, admin or the claiming mediator
+/// Protected: true
+#[patch("/<_address>/<oid>/assign/<mid>")]
+pub async fn assign_dispute(
+    _address: String,
+    oid: String,
+    mid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetDisputeResponse>> {
+    let m_dispute: models::Dispute = dispute::assign(&oid, &token.get_address(), &mid);
+    if m_dispute.did == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetDisputeResponse::build(m_dispute)),
+    )
+}
+
+/// Resolve a dispute with a release, refund or split, assigned mediator only
+/// Protected: true
+#[patch("/<_address>/<oid>/resolve/<resolution>?<split>")]
+pub async fn resolve_dispute(
+    _address: String,
+    oid: String,
+    resolution: String,
+    split: Option<u8>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetDisputeResponse>> {
+    let m_dispute: models::Dispute = dispute::resolve(&oid, &token.get_address(), &resolution, split).await;
+    if m_dispute.did == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetDisputeResponse::build(m_dispute)),
+    )
+}
+
+/// Create a mediator account
+/// Protected: true
+#[post("/<_address>", data = "<r_mediator>")]
+pub async fn create_mediator(
+    _address: String,
+    r_mediator: Json<reqres::MediatorRequest>,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetMediatorResponse>> {
+    let m_mediator: models::Mediator = mediator::create(&token.get_address(), &r_mediator);
+    if m_mediator.mid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Created,
+        Json(reqres::GetMediatorResponse::build(m_mediator)),
+    )
+}
+
+/// Get all mediators, admin only
+/// Protected: true
+#[get("/<_address>")]
+pub async fn get_mediators(
+    _address: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetMediatorsResponse>> {
+    let m_mediators: Vec<models::Mediator> = mediator::find_all(&token.get_address());
+    Custom(Status::Ok, Json(reqres::GetMediatorsResponse::build(m_mediators)))
+}
+
+/// Get a mediator, admin or the mediator only
+/// Protected: true
+#[get("/<_address>/<mid>")]
+pub async fn get_mediator(
+    _address: String,
+    mid: String,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetMediatorResponse>> {
+    let m_mediator: models::Mediator = mediator::find_for(&mid, &token.get_address());
+    if m_mediator.mid == utils::empty_string() {
+        return Custom(Status::NotFound, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetMediatorResponse::build(m_mediator)),
+    )
+}
+
+/// Activate or deactivate a mediator, admin only
+/// Protected: true
+#[patch("/<_address>/<mid>/<active>")]
+pub async fn update_mediator(
+    _address: String,
+    mid: String,
+    active: bool,
+    token: auth::BearerToken,
+) -> Custom<Json<reqres::GetMediatorResponse>> {
+    let m_mediator: models::Mediator = mediator::set_active(&mid, &token.get_address(), active);
+    if m_mediator.mid == utils::empty_string() {
+        return Custom(Status::BadRequest, Json(Default::default()));
+    }
+    Custom(
+        Status::Ok,
+        Json(reqres::GetMediatorResponse::build(m_mediator)),
+    )
+}
 // END JSON APIs
@@ -54,10 +54,13 @@ pub async fn verify_login(
         return auth::create(&address);
     }
     let data: String = String::from(&f_auth.rnd);
-    let sig_address: String =
-        monero::verify_signature(String::from(&address), data, String::from(&signature)).await;
-    if sig_address == utils::ApplicationErrors::LoginError.value() {
-        return f_auth;
+    match monero::verify_signature(String::from(&address), data, String::from(&signature)).await {
+        Ok(true) => (),
+        Ok(false) => return f_auth,
+        Err(e) => {
+            error!("can't verify signature for {}: {}", &address, e);
+            return f_auth;
+        }
     }
     let f_cust: Customer = find(&cvid);
     if f_cust.xmr_address == utils::empty_string() {
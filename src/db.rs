@@ -2,7 +2,7 @@
 extern crate lmdb_rs as lmdb;
 
 use log::error;
-use lmdb::{EnvBuilder, DbFlags, Environment, DbHandle};
+use lmdb::{Database, EnvBuilder, DbFlags, Environment, DbHandle};
 
 use crate::utils;
 
@@ -42,6 +42,57 @@ impl  Interface {
         }
         r
     }
+    /// Append a value to a comma separated list, e.g. ids for a vendor
+    pub fn append(e: &Environment, h: &DbHandle, k: &str, v: &str) {
+        Interface::transaction(e, h, |db| {
+            let old = String::from(db.get::<&str>(&k).unwrap_or(""));
+            let list = if old.is_empty() { String::from(v) } else { format!("{},{}", old, v) };
+            if db.set(&k, &list.as_str()).is_err() {
+                error!("failed to append to {}", k);
+                return false;
+            }
+            true
+        });
+    }
+    /// Remove a value from a comma separated list
+    pub fn remove_from_list(e: &Environment, h: &DbHandle, k: &str, v: &str) {
+        Interface::transaction(e, h, |db| {
+            let old = String::from(db.get::<&str>(&k).unwrap_or(""));
+            let list: Vec<&str> = old.split(',').filter(|s| !s.is_empty() && *s != v).collect();
+            let written = if list.is_empty() {
+                old.is_empty() || db.del::<>(&k).is_ok()
+            } else {
+                db.set(&k, &list.join(",").as_str()).is_ok()
+            };
+            if !written {
+                error!("failed to remove from {}", k);
+            }
+            written
+        });
+    }
+    /// Run reads and writes in a single write transaction. Changes are only
+    /// committed when `f` returns true, writers are serialized by lmdb.
+    pub fn transaction<F>(e: &Environment, h: &DbHandle, f: F) -> bool
+    where
+        F: FnOnce(&Database) -> bool,
+    {
+        let txn = e.new_transaction().unwrap();
+        let ok = {
+            let db = txn.bind(h);
+            f(&db)
+        };
+        if !ok {
+            txn.abort();
+            return false;
+        }
+        match txn.commit() {
+            Err(_) => {
+                error!("failed to commit!");
+                false
+            }
+            Ok(_) => true,
+        }
+    }
     pub fn delete(e: &Environment, h: &DbHandle, k: &str) {
         let txn = e.new_transaction().unwrap();
         {
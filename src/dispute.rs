@@ -1,41 +1,295 @@
-// use crate::models::*;
-// use crate::utils;
-// use crate::schema;
-// use diesel::prelude::*;
-// use log::{debug, error, info};
-
-// /// Create dispute
-// pub async fn create(oid: String, signed_tx: String) -> Dispute {
-//     info!("creating dispute");
-//     use crate::schema::disputes;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let did: String = utils::generate_rnd();
-//     let created: i64 = chrono::offset::Utc::now().timestamp();
-//     let new_dispute = NewDispute {
-//         did: &did,
-//         created: &created,
-//         orid: &oid,
-//         tx_set: &&signed_tx,
-//     };
-//     debug!("insert dispute: {:?}", new_dispute);
-//     diesel::insert_into(disputes::table)
-//         .values(&new_dispute)
-//         .get_result(connection)
-//         .expect("error saving new dispute")
-// }
-
-// /// Dispute lookup for manual resolution
-// pub async fn find(oid: String) -> Dispute {
-//     use self::schema::disputes::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let result = disputes
-//         .filter(schema::disputes::orid.eq(oid))
-//         .first::<Dispute>(connection);
-//     match result {
-//         Ok(r) => r,
-//         _ => {
-//             error!("error finding auth");
-//             Default::default()
-//         }
-//     }
-// }
+// Dispute repo/service layer
+use crate::{auth, db, mediator, models::*, order, utils};
+use log::{debug, error, info};
+
+/// Upper limit on the reason given when opening a dispute
+const MAX_REASON_LENGTH: usize = 1000;
+
+/// Dispute progress, disputes are queued until a mediator is assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeStatus {
+    Open,
+    Assigned,
+    Resolved,
+}
+
+impl DisputeStatus {
+    pub fn value(&self) -> String {
+        match *self {
+            DisputeStatus::Open => String::from("open"),
+            DisputeStatus::Assigned => String::from("assigned"),
+            DisputeStatus::Resolved => String::from("resolved"),
+        }
+    }
+}
+
+/// Disputes are keyed by the order they were opened against
+fn get_did(oid: &String) -> String {
+    format!("disp{}", oid)
+}
+
+/// Key for the list of orids with a dispute waiting for a mediator
+fn get_queue_key() -> String {
+    String::from("dq")
+}
+
+/// Key for the list of orids with a dispute assigned to a mediator
+fn get_list_key(mid: &String) -> String {
+    format!("dl{}", mid)
+}
+
+fn write(d: &Dispute) {
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &d.did);
+    db::Interface::write(&s.env, &s.handle, &d.did, &Dispute::to_db(d));
+}
+
+fn is_valid_reason(reason: &str) -> bool {
+    !reason.trim().is_empty() && reason.chars().count() <= MAX_REASON_LENGTH
+}
+
+/// Vendor percentage for the resolution. A split needs both parties to get
+/// something, a release or refund can't carry one.
+fn get_split(payout: order::PayoutType, split: Option<u8>) -> Option<u8> {
+    match (payout, split) {
+        (order::PayoutType::Split, Some(s)) if (1..100).contains(&s) => Some(s),
+        (order::PayoutType::Split, _) => None,
+        (_, None) => Some(0),
+        (_, Some(_)) => None,
+    }
+}
+
+/// The customer or vendor disputes a shipped order, which stops it from
+/// finalizing and queues it for a mediator
+pub fn create(oid: &String, address: &String, reason: String) -> Dispute {
+    if !is_valid_reason(&reason) {
+        error!("invalid dispute reason for order {}", oid);
+        return Default::default();
+    }
+    let m_order: Order = order::find(oid);
+    let role = match order::get_role(&m_order, address) {
+        Some(r @ (order::Role::Customer | order::Role::Vendor)) => r,
+        _ => {
+            error!("{} can't dispute order {}", address, oid);
+            return Default::default();
+        }
+    };
+    if find(oid).did != utils::empty_string() {
+        error!("order {} already has a dispute", oid);
+        return Default::default();
+    }
+    let u_order = order::set_status(oid, order::OrderStatus::Disputed, role);
+    if u_order.orid == utils::empty_string() {
+        return Default::default();
+    }
+    info!("{} opened a dispute on order {}", role.value(), oid);
+    let new_dispute = Dispute {
+        did: get_did(oid),
+        created: chrono::offset::Utc::now().timestamp(),
+        orid: String::from(oid),
+        opened_by: role.value(),
+        reason,
+        status: DisputeStatus::Open.value(),
+        ..Default::default()
+    };
+    debug!("insert dispute: {:?}", &new_dispute);
+    write(&new_dispute);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_queue_key(), oid);
+    new_dispute
+}
+
+/// Dispute lookup for manual resolution
+pub fn find(oid: &String) -> Dispute {
+    let did = get_did(oid);
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &did);
+    if r == utils::empty_string() {
+        return Default::default()
+    }
+    Dispute::from_db(did, r)
+}
+
+fn is_assigned(d: &Dispute, address: &String) -> bool {
+    d.m_id != utils::empty_string() && &mediator::find(&d.m_id).m_xmr_address == address
+}
+
+/// The address is the mediator assigned to the order's dispute
+pub fn is_mediator_for(oid: &String, address: &String) -> bool {
+    is_assigned(&find(oid), address)
+}
+
+/// Only the customer, vendor and assigned mediator can see a dispute
+pub fn find_for(oid: &String, address: &String) -> Dispute {
+    let f_dispute = find(oid);
+    if f_dispute.did == utils::empty_string() {
+        return f_dispute;
+    }
+    let m_order = order::find(oid);
+    let is_party = matches!(
+        order::get_role(&m_order, address),
+        Some(order::Role::Customer | order::Role::Vendor)
+    );
+    if !is_party && !is_assigned(&f_dispute, address) {
+        error!("{} can't view the dispute on order {}", address, oid);
+        return Default::default();
+    }
+    f_dispute
+}
+
+fn find_list(key: &str) -> Vec<Dispute> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, key);
+    split_list(r).iter().map(find).filter(|d| d.did != utils::empty_string()).collect()
+}
+
+/// Disputes waiting for a mediator. Reasons are only shown once assigned.
+pub fn find_queue(address: &String) -> Vec<Dispute> {
+    if !mediator::is_mediator(address) && !auth::is_admin(address) {
+        error!("{} can't view the dispute queue", address);
+        return Vec::new();
+    }
+    find_list(&get_queue_key())
+        .into_iter()
+        .map(|d| Dispute { reason: utils::empty_string(), ..d })
+        .collect()
+}
+
+/// Disputes assigned to the mediator owning the address
+pub fn find_assigned(address: &String) -> Vec<Dispute> {
+    let m_mediator = mediator::find_by_address(address);
+    if m_mediator.mid == utils::empty_string() {
+        return Vec::new();
+    }
+    find_list(&get_list_key(&m_mediator.mid))
+}
+
+/// The admin assigns a dispute to any active mediator, a mediator can
+/// claim a queued dispute for themselves. Mediators can't take disputes
+/// on orders they are a party to.
+pub fn assign(oid: &String, address: &String, mid: &String) -> Dispute {
+    let f_dispute = find(oid);
+    let m_mediator = mediator::find(mid);
+    let is_admin = auth::is_admin(address);
+    let is_claim = &m_mediator.m_xmr_address == address && f_dispute.status == DisputeStatus::Open.value();
+    if f_dispute.did == utils::empty_string() || f_dispute.status == DisputeStatus::Resolved.value() {
+        error!("no open dispute on order {}", oid);
+        return Default::default();
+    }
+    if !m_mediator.active || (!is_admin && !is_claim) {
+        error!("{} can't assign the dispute on order {} to {}", address, oid, mid);
+        return Default::default();
+    }
+    let m_order = order::find(oid);
+    if matches!(
+        order::get_role(&m_order, &m_mediator.m_xmr_address),
+        Some(order::Role::Customer | order::Role::Vendor)
+    ) {
+        error!("mediator {} is a party to order {}", mid, oid);
+        return Default::default();
+    }
+    info!("assigning the dispute on order {} to {}", oid, mid);
+    let s = db::Interface::open();
+    if f_dispute.m_id == utils::empty_string() {
+        db::Interface::remove_from_list(&s.env, &s.handle, &get_queue_key(), oid);
+    } else {
+        db::Interface::remove_from_list(&s.env, &s.handle, &get_list_key(&f_dispute.m_id), oid);
+    }
+    db::Interface::append(&s.env, &s.handle, &get_list_key(mid), oid);
+    let u_dispute = Dispute {
+        m_id: String::from(mid),
+        status: DisputeStatus::Assigned.value(),
+        ..f_dispute
+    };
+    write(&u_dispute);
+    u_dispute
+}
+
+/// The assigned mediator decides who gets the funds, or splits them with
+/// `split` percent to the vendor. The market key signs the payout: it
+/// co-signs a matching proposal from the winning party, or proposes one for
+/// the winner (either party on a split) to co-sign.
+pub async fn resolve(oid: &String, address: &String, resolution: &str, split: Option<u8>) -> Dispute {
+    let f_dispute = find(oid);
+    if f_dispute.status != DisputeStatus::Assigned.value() || !is_assigned(&f_dispute, address) {
+        error!("{} can't resolve the dispute on order {}", address, oid);
+        return Default::default();
+    }
+    let (payout, split) = match order::PayoutType::from_value(resolution).and_then(|p| Some((p, get_split(p, split)?))) {
+        Some(r) => r,
+        None => {
+            error!("invalid resolution {} {:?}", resolution, split);
+            return Default::default();
+        }
+    };
+    let m_order = order::find(oid);
+    if m_order.o_status != order::OrderStatus::Disputed.value() {
+        error!("order {} isn't disputed", oid);
+        return Default::default();
+    }
+    // the split terms are read back when the payout is built and co-signed
+    let f_dispute = Dispute { split, ..f_dispute };
+    write(&f_dispute);
+    let mediator = order::Role::Mediator.value();
+    let proposed = m_order.o_payout == payout.value()
+        && !m_order.o_signers.is_empty()
+        && !m_order.o_signers.contains(&mediator);
+    let u_order = if proposed {
+        order::sign_with_market_key(m_order).await
+    } else {
+        order::propose_with_market_key(m_order, payout).await
+    };
+    if u_order.o_payout != payout.value() || !u_order.o_signers.contains(&mediator) {
+        error!("failed to sign the {} for order {}", payout.value(), oid);
+        return Default::default();
+    }
+    info!("dispute on order {} resolved with a {}", oid, payout.value());
+    let u_dispute = Dispute {
+        status: DisputeStatus::Resolved.value(),
+        resolution: payout.value(),
+        resolved: chrono::offset::Utc::now().timestamp(),
+        ..f_dispute
+    };
+    write(&u_dispute);
+    u_dispute
+}
+
+/// Close a dispute the parties settled themselves with a co-signed payout
+pub fn settle(oid: &String, payout: order::PayoutType) {
+    let f_dispute = find(oid);
+    if f_dispute.did == utils::empty_string() || f_dispute.status == DisputeStatus::Resolved.value() {
+        return;
+    }
+    info!("dispute on order {} settled with a {}", oid, payout.value());
+    if f_dispute.m_id == utils::empty_string() {
+        let s = db::Interface::open();
+        db::Interface::remove_from_list(&s.env, &s.handle, &get_queue_key(), oid);
+    }
+    write(&Dispute {
+        status: DisputeStatus::Resolved.value(),
+        resolution: payout.value(),
+        resolved: chrono::offset::Utc::now().timestamp(),
+        ..f_dispute
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_split_test() {
+        assert_eq!(get_split(order::PayoutType::Split, Some(70)), Some(70));
+        assert_eq!(get_split(order::PayoutType::Split, Some(100)), None);
+        assert_eq!(get_split(order::PayoutType::Split, None), None);
+        assert_eq!(get_split(order::PayoutType::Release, None), Some(0));
+        assert_eq!(get_split(order::PayoutType::Refund, Some(30)), None);
+    }
+
+    #[test]
+    fn is_valid_reason_test() {
+        assert!(is_valid_reason("item never arrived"));
+        assert!(!is_valid_reason("  "));
+        assert!(!is_valid_reason(&"a".repeat(MAX_REASON_LENGTH + 1)));
+    }
+}
@@ -0,0 +1,258 @@
+// Dispute evidence and timeline. Statements and files are encrypted by the
+// submitter, the server only checks they are OpenPGP messages.
+use crate::{args, db, dispute, mediator, models::*, order, utils};
+use clap::Parser;
+use log::{debug, error, info};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// Upper limit on an armored statement
+const MAX_STATEMENT_LENGTH: usize = 64 * 1024;
+
+/// Statement or file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvidenceType {
+    Statement,
+    File,
+}
+
+impl EvidenceType {
+    pub fn value(&self) -> String {
+        match *self {
+            EvidenceType::Statement => String::from("statement"),
+            EvidenceType::File => String::from("file"),
+        }
+    }
+}
+
+/// One entry in a dispute timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    pub timestamp: i64,
+    /// status, dispute, resolution, statement or file
+    pub kind: String,
+    pub role: String,
+    /// new status, dispute reason, resolution, armored statement or evidence id
+    pub detail: String,
+}
+
+/// Get the evidence directory command line configuration
+fn get_evidence_dir() -> String {
+    let args = args::Args::parse();
+    args.evidence_dir
+}
+
+fn get_path(hash: &str) -> PathBuf {
+    PathBuf::from(get_evidence_dir()).join(format!("{}.pgp", hash))
+}
+
+/// Key for the list of eids on an order's dispute
+fn get_list_key(oid: &String) -> String {
+    format!("el{}", oid)
+}
+
+/// ASCII armored OpenPGP message, or a binary one starting with a public
+/// key encrypted session key packet (tag 1)
+pub fn is_pgp_message(data: &[u8]) -> bool {
+    let armored = std::str::from_utf8(data)
+        .map(|s| {
+            let s = s.trim();
+            s.starts_with("-----BEGIN PGP MESSAGE-----") && s.ends_with("-----END PGP MESSAGE-----")
+        })
+        .unwrap_or(false);
+    let tag = match data.first() {
+        Some(b) if b & 0x80 == 0 => 0,
+        // new packet format keeps the tag in the low six bits
+        Some(b) if b & 0x40 != 0 => b & 0x3f,
+        Some(b) => (b & 0x3c) >> 2,
+        None => 0,
+    };
+    armored || tag == 1
+}
+
+/// Role of the submitter on an assigned dispute. Evidence is encrypted to
+/// the mediator, so nothing can be added until one with a key is assigned.
+fn get_submitter(oid: &String, address: &String) -> Option<order::Role> {
+    let d = dispute::find(oid);
+    if d.status != dispute::DisputeStatus::Assigned.value() {
+        error!("dispute on order {} isn't assigned", oid);
+        return None;
+    }
+    let m_mediator = mediator::find(&d.m_id);
+    if m_mediator.m_pgp == utils::empty_string() {
+        error!("mediator {} has no pgp key", &d.m_id);
+        return None;
+    }
+    match order::get_role(&order::find(oid), address) {
+        Some(r @ (order::Role::Customer | order::Role::Vendor)) => Some(r),
+        _ if &m_mediator.m_xmr_address == address => Some(order::Role::Mediator),
+        _ => None,
+    }
+}
+
+fn create(oid: &String, role: order::Role, kind: EvidenceType, data: String) -> Evidence {
+    let new_evidence = Evidence {
+        eid: format!("ev{}", utils::generate_rnd()),
+        orid: String::from(oid),
+        role: role.value(),
+        kind: kind.value(),
+        created: chrono::offset::Utc::now().timestamp(),
+        data,
+    };
+    debug!("insert evidence: {:?}", &new_evidence);
+    let s = db::Interface::open();
+    db::Interface::write(&s.env, &s.handle, &new_evidence.eid, &Evidence::to_db(&new_evidence));
+    db::Interface::append(&s.env, &s.handle, &get_list_key(oid), &new_evidence.eid);
+    new_evidence
+}
+
+/// Add an armored statement. Parties encrypt to the mediator's key, the
+/// mediator encrypts to the parties' keys.
+pub fn add_statement(oid: &String, address: &String, statement: String) -> Evidence {
+    if statement.len() > MAX_STATEMENT_LENGTH || !is_pgp_message(statement.as_bytes()) {
+        error!("statement on order {} isn't an armored pgp message", oid);
+        return Default::default();
+    }
+    let role = match get_submitter(oid, address) {
+        Some(r) => r,
+        None => return Default::default(),
+    };
+    info!("{} added a statement to the dispute on order {}", role.value(), oid);
+    create(oid, role, EvidenceType::Statement, statement)
+}
+
+/// Store an encrypted evidence file, addressed by the hex sha256 of its contents
+pub fn add_file(oid: &String, address: &String, data: &[u8]) -> Evidence {
+    if !is_pgp_message(data) {
+        error!("evidence on order {} isn't a pgp message", oid);
+        return Default::default();
+    }
+    let role = match get_submitter(oid, address) {
+        Some(r) => r,
+        None => return Default::default(),
+    };
+    let hash = hex::encode(Sha256::digest(data));
+    let path = get_path(&hash);
+    if !path.exists() {
+        let written = fs::create_dir_all(get_evidence_dir()).and_then(|_| fs::write(&path, data));
+        if let Err(e) = written {
+            error!("failed to write evidence {}: {}", hash, e);
+            return Default::default();
+        }
+    }
+    info!("{} added evidence {} to the dispute on order {}", role.value(), hash, oid);
+    create(oid, role, EvidenceType::File, hash)
+}
+
+/// Lookup all evidence on an order's dispute
+fn find_all(oid: &String) -> Vec<Evidence> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key(oid));
+    split_list(r)
+        .iter()
+        .map(|eid| Evidence::from_db(String::from(eid), db::Interface::read(&s.env, &s.handle, eid)))
+        .collect()
+}
+
+/// Read an evidence file for the parties or assigned mediator, empty if not allowed
+pub fn load_file(oid: &String, eid: &String, address: &String) -> Vec<u8> {
+    if dispute::find_for(oid, address).did == utils::empty_string() {
+        return Vec::new();
+    }
+    let evidence = find_all(oid).into_iter().find(|e| &e.eid == eid && e.kind == EvidenceType::File.value());
+    match evidence {
+        Some(e) => fs::read(get_path(&e.data)).unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
+/// Status changes, dispute events and evidence in the order they happened
+pub fn build_timeline(o: &Order, d: &Dispute, evidence: &[Evidence]) -> Vec<TimelineEvent> {
+    let mut events: Vec<TimelineEvent> = o
+        .o_history
+        .iter()
+        .map(|c| TimelineEvent {
+            timestamp: c.timestamp,
+            kind: String::from("status"),
+            role: String::from(&c.role),
+            detail: String::from(&c.status),
+        })
+        .collect();
+    events.push(TimelineEvent {
+        timestamp: d.created,
+        kind: String::from("dispute"),
+        role: String::from(&d.opened_by),
+        detail: String::from(&d.reason),
+    });
+    if d.resolved > 0 {
+        events.push(TimelineEvent {
+            timestamp: d.resolved,
+            kind: String::from("resolution"),
+            role: order::Role::Mediator.value(),
+            detail: if d.split > 0 {
+                format!("{} {}/{}", &d.resolution, d.split, 100 - d.split)
+            } else {
+                String::from(&d.resolution)
+            },
+        });
+    }
+    events.extend(evidence.iter().map(|e| TimelineEvent {
+        timestamp: e.created,
+        kind: String::from(&e.kind),
+        role: String::from(&e.role),
+        detail: if e.kind == EvidenceType::File.value() { String::from(&e.eid) } else { String::from(&e.data) },
+    }));
+    events.sort_by_key(|e| e.timestamp);
+    events
+}
+
+/// Dispute timeline for the parties or assigned mediator
+pub fn timeline(oid: &String, address: &String) -> Vec<TimelineEvent> {
+    let d = dispute::find_for(oid, address);
+    if d.did == utils::empty_string() {
+        return Vec::new();
+    }
+    build_timeline(&order::find(oid), &d, &find_all(oid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_pgp_message_test() {
+        let armored = "-----BEGIN PGP MESSAGE-----\n\nhQEMA...\n-----END PGP MESSAGE-----\n";
+        assert!(is_pgp_message(armored.as_bytes()));
+        // new and old format public key encrypted session key packets
+        assert!(is_pgp_message(&[0xc1, 0x0c]));
+        assert!(is_pgp_message(&[0x84, 0x0c]));
+        // literal data packet, not encrypted
+        assert!(!is_pgp_message(&[0xcb, 0x0c]));
+        assert!(!is_pgp_message(b"plain text"));
+        assert!(!is_pgp_message(&[]));
+    }
+
+    #[test]
+    fn build_timeline_test() {
+        let o = Order {
+            o_history: vec![
+                StatusChange { status: String::from("Shipped"), role: String::from("vendor"), timestamp: 10 },
+                StatusChange { status: String::from("Disputed"), role: String::from("customer"), timestamp: 20 },
+            ],
+            ..Default::default()
+        };
+        let d = Dispute { created: 20, opened_by: String::from("customer"), resolved: 40, ..Default::default() };
+        let evidence = vec![Evidence {
+            eid: String::from("ev1"),
+            kind: EvidenceType::File.value(),
+            created: 30,
+            data: String::from("hash"),
+            ..Default::default()
+        }];
+        let events = build_timeline(&o, &d, &evidence);
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["status", "status", "dispute", "file", "resolution"]);
+        assert_eq!(events[3].detail, "ev1");
+    }
+}
@@ -0,0 +1,152 @@
+// Feedback repo/service layer
+use crate::{customer, db, models::*, order, utils, vendor};
+use log::{debug, error, info};
+
+/// Upper limit on feedback comment and reply length
+const MAX_COMMENT_LENGTH: usize = 280;
+
+/// One feedback per order, so the order id determines the key
+fn get_fid(oid: &String) -> String {
+    format!("fb{}", oid)
+}
+
+/// Key for the list of fids belonging to a vendor or product
+fn get_list_key(id: &String) -> String {
+    format!("fl{}", id)
+}
+
+fn write(f: &Feedback) {
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &f.fid);
+    db::Interface::write(&s.env, &s.handle, &f.fid, &Feedback::to_db(f));
+}
+
+/// Ratings are 1 - 5 stars with a short comment
+fn is_valid(rating: u8, comment: &str) -> bool {
+    (1..=5).contains(&rating) && comment.chars().count() <= MAX_COMMENT_LENGTH
+}
+
+/// Customer feedback on a delivered order
+pub fn create(oid: &String, address: &String, rating: u8, comment: String) -> Feedback {
+    if !is_valid(rating, &comment) {
+        error!("invalid feedback for order {}", oid);
+        return Default::default();
+    }
+    let m_order: Order = order::find(oid);
+    let m_customer: Customer = customer::find(&m_order.c_id);
+    if m_order.orid == utils::empty_string() || &m_customer.xmr_address != address {
+        error!("feedback not allowed on order {}", oid);
+        return Default::default();
+    }
+    let delivered = [order::OrderStatus::Delivered.value(), order::OrderStatus::Released.value()];
+    if !delivered.contains(&m_order.o_status) {
+        error!("order {} has not been delivered", oid);
+        return Default::default();
+    }
+    if find(oid).fid != utils::empty_string() {
+        error!("feedback already exists for order {}", oid);
+        return Default::default();
+    }
+    info!("creating feedback for order {}", oid);
+    let new_feedback = Feedback {
+        fid: get_fid(oid),
+        orid: String::from(oid),
+        c_id: m_order.c_id,
+        p_id: m_order.p_id,
+        v_id: m_order.v_id,
+        rating,
+        comment,
+        created: chrono::offset::Utc::now().timestamp(),
+        reply: utils::empty_string(),
+        reply_date: 0,
+    };
+    debug!("insert feedback: {:?}", &new_feedback);
+    write(&new_feedback);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_list_key(&new_feedback.v_id), &new_feedback.fid);
+    db::Interface::append(&s.env, &s.handle, &get_list_key(&new_feedback.p_id), &new_feedback.fid);
+    new_feedback
+}
+
+/// The vendor gets a single reply to feedback
+pub fn reply(oid: &String, address: &String, reply: String) -> Feedback {
+    let f_feedback: Feedback = find(oid);
+    if f_feedback.fid == utils::empty_string() || reply.chars().count() > MAX_COMMENT_LENGTH {
+        error!("invalid feedback reply for order {}", oid);
+        return Default::default();
+    }
+    let m_vendor: Vendor = vendor::find(&f_feedback.v_id);
+    if &m_vendor.v_xmr_address != address {
+        error!("reply not allowed on order {}", oid);
+        return Default::default();
+    }
+    if f_feedback.reply != utils::empty_string() {
+        error!("feedback for order {} already has a reply", oid);
+        return Default::default();
+    }
+    info!("replying to feedback for order {}", oid);
+    let now: i64 = chrono::offset::Utc::now().timestamp();
+    let u_feedback = Feedback::update_reply(f_feedback, reply, now);
+    write(&u_feedback);
+    u_feedback
+}
+
+/// Feedback lookup by order
+pub fn find(oid: &String) -> Feedback {
+    let fid = get_fid(oid);
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &fid);
+    if r == utils::empty_string() {
+        return Default::default()
+    }
+    Feedback::from_db(fid, r)
+}
+
+/// Lookup all feedback for a vendor or product
+pub fn find_all(id: &String) -> Vec<Feedback> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key(id));
+    if r == utils::empty_string() {
+        info!("no feedback found for {}", id);
+        return Vec::new()
+    }
+    r.split(',')
+        .map(|fid| {
+            let v = db::Interface::read(&s.env, &s.handle, fid);
+            Feedback::from_db(String::from(fid), v)
+        })
+        .collect()
+}
+
+/// Average rating, zero when there is no feedback
+pub fn score(feedback: &[Feedback]) -> f64 {
+    if feedback.is_empty() {
+        return 0.0;
+    }
+    let total: u64 = feedback.iter().map(|f| f.rating as u64).sum();
+    total as f64 / feedback.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_valid_test() {
+        assert!(is_valid(1, "ok"));
+        assert!(is_valid(5, ""));
+        assert!(!is_valid(0, "ok"));
+        assert!(!is_valid(6, "ok"));
+        assert!(!is_valid(3, &"a".repeat(MAX_COMMENT_LENGTH + 1)));
+    }
+
+    #[test]
+    fn score_test() {
+        let feedback = vec![
+            Feedback { rating: 5, ..Default::default() },
+            Feedback { rating: 2, ..Default::default() },
+        ];
+        assert_eq!(score(&feedback), 3.5);
+        assert_eq!(score(&Vec::new()), 0.0);
+    }
+}
@@ -1,17 +1,29 @@
 pub mod args;       // Command line arguments
 pub mod auth;       // Authorization repo/service layer
+pub mod bond;       // Vendor bond repo/service layer
+pub mod commission; // Market commission on releases
 pub mod controller; // HTTP entry point
 pub mod customer;   // Customer repo/service layer
 pub mod db;         // lmdb interface
 pub mod dispute;    // Dispute repo/service layer
+pub mod evidence;   // Dispute evidence and timeline
+pub mod feedback;   // Feedback repo/service layer
 pub mod i2p;        // I2P repo/service layer
-// TODO: mediator layer
+pub mod mediator;   // Mediator repo/service layer
+pub mod media;      // Product image storage
 pub mod models;     // Models for database query/insertion
 pub mod monero;     // Monero-wallet-rpc interface
 pub mod order;      // Order repo/service layer
 pub mod product;    // Product repo/service layer
+pub mod rates;      // Exchange rate providers
 pub mod reqres;     // HTTP request/responses
+pub mod rpc;        // JSON-RPC client for monero-wallet-rpc
+pub mod scheduler;  // Background order jobs
+pub mod search;     // Product search index
+pub mod shipping;   // Shipping option repo/service layer
+pub mod supervisor; // monero-wallet-rpc process supervisor
 pub mod utils;      // Misc.
 pub mod vendor;     // Vendor repo/service layer
+pub mod wallet;     // Wallet sessions on monero-wallet-rpc
 
 // DO NOT EDIT BELOW THIS LINE
@@ -12,11 +12,32 @@ async fn rocket() -> _ {
     rocket::build()
         .mount("/", routes![controller::login])
         .mount("/customer", routes![controller::get_customer /*controller::update_customer*/])
-        // .mount("/vendor", routes![controller::get_vendor, controller::update_vendor])
-        // .mount("/product", routes![controller::create_product, controller::update_product])
-        // .mount("/products", routes![controller::get_vendor_products])
-        // .mount("/order", routes![controller::initialize_order, controller::update_order])
-        // .mount("/orders", routes![controller::get_orders])
+        .mount("/vendor", routes![controller::get_vendor, controller::get_vendor_bond,
+            controller::get_vendor_profile, controller::activate_vendor /*controller::update_vendor*/])
+        .mount("/bond", routes![controller::resolve_bond])
+        .mount("/feedback", routes![controller::create_feedback, controller::reply_feedback,
+            controller::get_vendor_feedback, controller::get_product_feedback])
+        .mount("/product", routes![controller::create_product, controller::get_product,
+            controller::update_product, controller::delete_product,
+            controller::upload_product_image, controller::delete_product_image,
+            controller::create_variant, controller::update_variant, controller::delete_variant])
+        .mount("/shipping", routes![controller::create_shipping, controller::get_vendor_shipping,
+            controller::update_shipping, controller::delete_shipping])
+        .mount("/image", routes![controller::get_image])
+        .mount("/products", routes![controller::get_vendor_products])
+        .mount("/search", routes![controller::search_products])
+        .mount("/order", routes![controller::create_order, controller::get_order,
+            controller::update_order])
+        .mount("/orders", routes![controller::get_orders])
+        .mount("/dispute", routes![controller::create_dispute, controller::get_dispute,
+            controller::get_dispute_queue, controller::get_assigned_disputes,
+            controller::assign_dispute, controller::resolve_dispute,
+            controller::add_dispute_statement, controller::upload_dispute_evidence,
+            controller::get_dispute_evidence, controller::get_dispute_timeline])
+        .mount("/mediator", routes![controller::create_mediator, controller::get_mediators,
+            controller::get_mediator, controller::update_mediator])
+        .mount("/commission", routes![controller::get_commissions, controller::get_commission_report,
+            controller::set_commission, controller::delete_commission])
         // .mount("/xmr", routes![controller::get_version])
-        // .mount("/dispute", routes![controller::create_dispute, controller::get_dispute])
+        .attach(supervisor::fairing())
 }
@@ -0,0 +1,153 @@
+// Product image storage, images are re-encoded to strip metadata
+use crate::{args, db, utils};
+use clap::Parser;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::ImageFormat;
+use log::{debug, error, info};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// Most images a single product can have
+pub const MAX_IMAGES: usize = 5;
+
+/// Images are scaled to fit within a square of this many pixels
+const IMAGE_SIZE: u32 = 800;
+const JPEG_QUALITY: u8 = 85;
+
+/// Get the image directory command line configuration
+fn get_image_dir() -> String {
+    let args = args::Args::parse();
+    args.image_dir
+}
+
+fn get_path(hash: &str) -> PathBuf {
+    PathBuf::from(get_image_dir()).join(format!("{}.jpg", hash))
+}
+
+/// Key for the list of pids that reference an image
+fn get_ref_key(hash: &str) -> String {
+    format!("img{}", hash)
+}
+
+/// Images are addressed by the hex sha256 of the re-encoded file
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Check the file type, then decode, resize and re-encode as jpeg.
+/// Only pixel data survives decoding so EXIF, GPS and comments are dropped.
+pub fn process(data: &[u8]) -> Option<Vec<u8>> {
+    let format = match image::guess_format(data) {
+        Ok(f @ (ImageFormat::Jpeg | ImageFormat::Png)) => f,
+        _ => {
+            error!("unsupported image type");
+            return None;
+        }
+    };
+    let decoded = match image::load_from_memory_with_format(data, format) {
+        Ok(i) => i,
+        Err(e) => {
+            error!("failed to decode image: {}", e);
+            return None;
+        }
+    };
+    let resized = decoded.resize(IMAGE_SIZE, IMAGE_SIZE, FilterType::Lanczos3).to_rgb8();
+    let mut out: Vec<u8> = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+    match encoder.encode_image(&resized) {
+        Ok(_) => Some(out),
+        Err(e) => {
+            error!("failed to encode image: {}", e);
+            None
+        }
+    }
+}
+
+/// Hash a processed image is stored under
+pub fn get_hash(processed: &[u8]) -> String {
+    hex::encode(Sha256::digest(processed))
+}
+
+/// Write a processed image and reference it from the product, false on failure
+pub fn store(pid: &String, hash: &str, processed: &[u8]) -> bool {
+    let path = get_path(hash);
+    if !path.exists() {
+        let written = fs::create_dir_all(get_image_dir()).and_then(|_| fs::write(&path, processed));
+        if let Err(e) = written {
+            error!("failed to write image {}: {}", hash, e);
+            return false;
+        }
+    }
+    info!("stored image {} for product {}", hash, pid);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_ref_key(hash), pid);
+    true
+}
+
+/// Read an image by hash, empty if it doesn't exist
+pub fn load(hash: &str) -> Vec<u8> {
+    if !is_valid_hash(hash) {
+        return Vec::new();
+    }
+    fs::read(get_path(hash)).unwrap_or_default()
+}
+
+/// Drop the product reference and delete the file once nothing uses it
+pub fn release(pid: &str, hash: &str) {
+    let s = db::Interface::open();
+    let key = get_ref_key(hash);
+    db::Interface::remove_from_list(&s.env, &s.handle, &key, pid);
+    let r = db::Interface::read(&s.env, &s.handle, &key);
+    if r == utils::empty_string() {
+        debug!("deleting unused image {}", hash);
+        if let Err(e) = fs::remove_file(get_path(hash)) {
+            error!("failed to delete image {}: {}", hash, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageOutputFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn test_jpeg() -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(1600, 400));
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, ImageOutputFormat::Jpeg(90)).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn process_strips_exif_test() {
+        let jpeg = test_jpeg();
+        // insert an APP1 Exif segment right after the SOI marker
+        let exif = b"Exif\0\0GPS-LOCATION";
+        let mut tagged: Vec<u8> = jpeg[..2].to_vec();
+        tagged.extend_from_slice(&[0xFF, 0xE1, 0x00, (exif.len() + 2) as u8]);
+        tagged.extend_from_slice(exif);
+        tagged.extend_from_slice(&jpeg[2..]);
+        let processed = process(&tagged).unwrap();
+        let needle = b"GPS-LOCATION";
+        assert!(tagged.windows(needle.len()).any(|w| w == needle));
+        assert!(!processed.windows(needle.len()).any(|w| w == needle));
+        let out = image::load_from_memory(&processed).unwrap();
+        assert_eq!((out.width(), out.height()), (IMAGE_SIZE, IMAGE_SIZE / 4));
+    }
+
+    #[test]
+    fn process_rejects_non_images_test() {
+        assert!(process(b"GIF89a not allowed").is_none());
+        assert!(process(b"plain text").is_none());
+    }
+
+    #[test]
+    fn is_valid_hash_test() {
+        assert!(is_valid_hash(&"a".repeat(64)));
+        assert!(!is_valid_hash("../../etc/passwd"));
+        assert!(!is_valid_hash(&"g".repeat(64)));
+    }
+}
@@ -1 +1,152 @@
-// TODO: something
\ No newline at end of file
+// Mediator repo/service layer
+use crate::{auth, db, models::*, monero, reqres, utils};
+use log::{debug, error, info};
+
+/// Key for the list of every mediator
+fn get_list_key() -> String {
+    String::from("mdl")
+}
+
+/// Key mapping an xmr address to its mediator account
+fn get_address_key(address: &String) -> String {
+    format!("mda{}", address)
+}
+
+fn write(m: &Mediator) {
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &m.mid);
+    db::Interface::write(&s.env, &s.handle, &m.mid, &Mediator::to_db(m));
+}
+
+/// Mediator accounts are created by the admin for an existing xmr address
+pub fn create(address: &String, r: &reqres::MediatorRequest) -> Mediator {
+    if !auth::is_admin(address) {
+        error!("{} can't create mediators", address);
+        return Default::default();
+    }
+    if r.xmr_address == utils::empty_string() || find_by_address(&r.xmr_address).mid != utils::empty_string() {
+        error!("invalid or existing mediator address {}", &r.xmr_address);
+        return Default::default();
+    }
+    let new_mediator = Mediator {
+        mid: format!("med{}", utils::generate_rnd()),
+        m_xmr_address: String::from(&r.xmr_address),
+        m_name: String::from(&r.name),
+        m_pgp: String::from(&r.pgp),
+        active: true,
+        created: chrono::offset::Utc::now().timestamp(),
+    };
+    debug!("insert mediator: {:?}", &new_mediator);
+    write(&new_mediator);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_list_key(), &new_mediator.mid);
+    db::Interface::write(&s.env, &s.handle, &get_address_key(&new_mediator.m_xmr_address), &new_mediator.mid);
+    new_mediator
+}
+
+/// Mediator lookup
+pub fn find(mid: &String) -> Mediator {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, mid);
+    if r == utils::empty_string() {
+        return Default::default()
+    }
+    Mediator::from_db(String::from(mid), r)
+}
+
+/// Mediator lookup by xmr address
+pub fn find_by_address(address: &String) -> Mediator {
+    let s = db::Interface::open();
+    let mid = db::Interface::read(&s.env, &s.handle, &get_address_key(address));
+    if mid == utils::empty_string() {
+        return Default::default()
+    }
+    find(&mid)
+}
+
+/// Lookup all mediators, admin only
+pub fn find_all(address: &String) -> Vec<Mediator> {
+    if !auth::is_admin(address) {
+        error!("{} can't list mediators", address);
+        return Vec::new();
+    }
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key());
+    split_list(r).iter().map(find).filter(|m| m.mid != utils::empty_string()).collect()
+}
+
+/// Mediator lookup for the admin or the mediator
+pub fn find_for(mid: &String, address: &String) -> Mediator {
+    let f_mediator = find(mid);
+    if &f_mediator.m_xmr_address != address && !auth::is_admin(address) {
+        return Default::default();
+    }
+    f_mediator
+}
+
+/// The address belongs to an active mediator
+pub fn is_mediator(address: &String) -> bool {
+    find_by_address(address).active
+}
+
+/// Inactive mediators keep their disputes but can't take new ones
+pub fn set_active(mid: &String, address: &String, active: bool) -> Mediator {
+    if !auth::is_admin(address) {
+        error!("{} can't update mediators", address);
+        return Default::default();
+    }
+    let f_mediator = find(mid);
+    if f_mediator.mid == utils::empty_string() {
+        error!("mediator {} not found", mid);
+        return f_mediator;
+    }
+    info!("setting mediator {} active: {}", mid, active);
+    let u_mediator = Mediator { active, ..f_mediator };
+    write(&u_mediator);
+    u_mediator
+}
+
+/// Performs the signature verfication against stored auth. Unlike customers
+/// and vendors, logging in never creates a mediator account.
+pub async fn verify_login(
+    address: String,
+    aid: String,
+    mid: String,
+    signature: String,
+) -> Authorization {
+    let f_auth: Authorization = auth::find(&aid);
+    if f_auth.xmr_address == utils::empty_string() {
+        return auth::create(&address);
+    }
+    let data: String = String::from(&f_auth.rnd);
+    match monero::verify_signature(String::from(&address), data, String::from(&signature)).await {
+        Ok(true) => (),
+        Ok(false) => return f_auth,
+        Err(e) => {
+            error!("can't verify signature for {}: {}", &address, e);
+            return f_auth;
+        }
+    }
+    let f_mediator: Mediator = find(&mid);
+    if f_mediator.mid == utils::empty_string() {
+        let m_mediator = find_by_address(&address);
+        if m_mediator.mid == utils::empty_string() {
+            error!("no mediator account for {}", &address);
+            return Default::default();
+        }
+        info!("linking mediator {}", &m_mediator.mid);
+        let u_auth = Authorization::update_cvid(f_auth, String::from(&m_mediator.mid));
+        let s = db::Interface::open();
+        db::Interface::delete(&s.env, &s.handle, &u_auth.aid);
+        db::Interface::write(&s.env, &s.handle, &u_auth.aid, &Authorization::to_db(&u_auth));
+        u_auth
+    } else {
+        info!("returning mediator");
+        if f_mediator.m_xmr_address != address {
+            return Default::default();
+        }
+        let m_access = auth::verify_access(&address, &signature).await;
+        if !m_access { return Default::default() }
+        f_auth
+    }
+}
@@ -48,47 +48,219 @@ pub struct Vendor {
     pub v_description: String,
     pub v_pgp: String,
     pub active: bool,
+    pub created: i64,
 }
 
 impl Default for Vendor {
     fn default() -> Self {
         Vendor {
-            vid: String::from(""),
-            v_xmr_address: String::from(""),
-            v_name: String::from(""),
-            v_description: String::from(""),
-            v_pgp: String::from(""),
+            vid: utils::empty_string(),
+            v_xmr_address: utils::empty_string(),
+            v_name: utils::empty_string(),
+            v_description: utils::empty_string(),
+            v_pgp: utils::empty_string(),
             active: false,
+            created: 0,
         }
     }
 }
 
-#[derive(Debug)]
+impl Vendor {
+    pub fn to_db(v: &Vendor) -> String {
+        format!("{}:{}:{}:{}:{}:{}",
+            v.active, v.created, hex::encode(&v.v_description), hex::encode(&v.v_name), hex::encode(&v.v_pgp),
+            v.v_xmr_address)
+    }
+    pub fn from_db(k: String, v: String) -> Vendor {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let active = v.remove(0).parse::<bool>().unwrap_or(false);
+        let created = v.remove(0).parse::<i64>().unwrap_or(0);
+        let v_description = utils::decode_hex(&v.remove(0));
+        let v_name = utils::decode_hex(&v.remove(0));
+        let v_pgp = utils::decode_hex(&v.remove(0));
+        let v_xmr_address = v.remove(0);
+        Vendor { vid: k, v_xmr_address, v_name, v_description, v_pgp, active, created }
+    }
+    pub fn update_active(v: Vendor, active: bool) -> Vendor {
+        Vendor {
+            vid: v.vid,
+            v_xmr_address: v.v_xmr_address,
+            v_name: v.v_name,
+            v_description: v.v_description,
+            v_pgp: v.v_pgp,
+            active,
+            created: v.created,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Product {
     pub pid: String,
     pub v_id: String,
     pub in_stock: bool,
     pub p_description: String,
     pub p_name: String,
+    /// piconero, or cents of `currency` when one is set
     pub p_price: i64,
     pub qty: i64,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub images: Vec<String>,
+    pub variants: Vec<Variant>,
+    /// units held by unfunded orders
+    pub reserved: i64,
+    /// fiat currency code for product and variant prices, empty for xmr
+    pub currency: String,
 }
 
 impl Default for Product {
     fn default() -> Self {
         Product {
-            pid: String::from(""),
-            v_id: String::from(""),
+            pid: utils::empty_string(),
+            v_id: utils::empty_string(),
             in_stock: false,
-            p_description: String::from(""),
-            p_name: String::from(""),
+            p_description: utils::empty_string(),
+            p_name: utils::empty_string(),
             p_price: 0,
             qty: 0,
+            category: utils::empty_string(),
+            tags: Vec::new(),
+            images: Vec::new(),
+            variants: Vec::new(),
+            reserved: 0,
+            currency: utils::empty_string(),
+        }
+    }
+}
+
+impl Product {
+    /// name, description, category and tags are free text so they are stored hex encoded
+    pub fn to_db(p: &Product) -> String {
+        let variants: Vec<String> = p.variants.iter().map(Variant::to_db).collect();
+        format!("{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            hex::encode(&p.category), p.currency, p.images.join(","), p.in_stock, hex::encode(&p.p_description),
+            hex::encode(&p.p_name), p.p_price, p.qty, p.reserved, hex::encode(p.tags.join(",")),
+            p.v_id, variants.join(","))
+    }
+    pub fn from_db(k: String, v: String) -> Product {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let category = utils::decode_hex(&v.remove(0));
+        let currency = v.remove(0);
+        let images: Vec<String> = v.remove(0)
+            .split(',')
+            .filter(|i| !i.is_empty())
+            .map(String::from)
+            .collect();
+        let in_stock = v.remove(0).parse::<bool>().unwrap_or(false);
+        let p_description = utils::decode_hex(&v.remove(0));
+        let p_name = utils::decode_hex(&v.remove(0));
+        let p_price = v.remove(0).parse::<i64>().unwrap_or(0);
+        let qty = v.remove(0).parse::<i64>().unwrap_or(0);
+        let reserved = v.remove(0).parse::<i64>().unwrap_or(0);
+        let tags: Vec<String> = utils::decode_hex(&v.remove(0))
+            .split(',')
+            .filter(|t| !t.is_empty())
+            .map(String::from)
+            .collect();
+        let v_id = v.remove(0);
+        let variants: Vec<Variant> = v.remove(0)
+            .split(',')
+            .filter(|r| !r.is_empty())
+            .map(Variant::from_db)
+            .collect();
+        Product {
+            pid: k, v_id, in_stock, p_description, p_name, p_price, qty, category, tags, images, variants,
+            reserved, currency,
+        }
+    }
+}
+
+/// Product option (size, weight, etc.) with its own price and stock.
+/// Variants are stored inside the product record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub vrid: String,
+    pub name: String,
+    pub price: i64,
+    pub qty: i64,
+    pub reserved: i64,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant {
+            vrid: utils::empty_string(),
+            name: utils::empty_string(),
+            price: 0,
+            qty: 0,
+            reserved: 0,
         }
     }
 }
 
+impl Variant {
+    pub fn to_db(v: &Variant) -> String {
+        format!("{};{};{};{};{}", v.vrid, hex::encode(&v.name), v.price, v.qty, v.reserved)
+    }
+    pub fn from_db(r: &str) -> Variant {
+        let mut v: Vec<String> = r.split(';').map(String::from).collect();
+        let vrid = v.remove(0);
+        let name = utils::decode_hex(&v.remove(0));
+        let price = v.remove(0).parse::<i64>().unwrap_or(0);
+        let qty = v.remove(0).parse::<i64>().unwrap_or(0);
+        let reserved = v.remove(0).parse::<i64>().unwrap_or(0);
+        Variant { vrid, name, price, qty, reserved }
+    }
+}
+
+/// Vendor defined shipping method
 #[derive(Debug)]
+pub struct Shipping {
+    pub sid: String,
+    pub v_id: String,
+    pub name: String,
+    /// always piconero
+    pub price: i64,
+    /// destination regions, empty ships anywhere
+    pub regions: Vec<String>,
+}
+
+impl Default for Shipping {
+    fn default() -> Self {
+        Shipping {
+            sid: utils::empty_string(),
+            v_id: utils::empty_string(),
+            name: utils::empty_string(),
+            price: 0,
+            regions: Vec::new(),
+        }
+    }
+}
+
+impl Shipping {
+    pub fn to_db(s: &Shipping) -> String {
+        format!("{}:{}:{}:{}",
+            hex::encode(&s.name), s.price, hex::encode(s.regions.join(",")), s.v_id)
+    }
+    pub fn from_db(k: String, v: String) -> Shipping {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let name = utils::decode_hex(&v.remove(0));
+        let price = v.remove(0).parse::<i64>().unwrap_or(0);
+        let regions: Vec<String> = utils::decode_hex(&v.remove(0))
+            .split(',')
+            .filter(|r| !r.is_empty())
+            .map(String::from)
+            .collect();
+        let v_id = v.remove(0);
+        Shipping { sid: k, v_id, name, price, regions }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Order {
     pub orid: String,
     pub c_id: String,
@@ -96,27 +268,53 @@ pub struct Order {
     pub v_id: String,
     pub o_xmr_address: String,
     pub o_cust_msig_info: String,
-    pub o_cust_msig_txset: String,
-    pub o_cust_kex_1: String,
-    pub o_cust_kex_2: String,
-    pub o_cust_kex_3: String,
+    /// key exchange messages, one per round starting with make_multisig
+    pub o_cust_kex: Vec<String>,
+    pub o_cust_msig_export: String,
     pub o_date: i64,
     pub o_deliver_date: i64,
+    /// market commission in piconero, paid out of a release
+    pub o_fee: i64,
+    /// market wallet subaddress the commission is paid to
+    pub o_fee_address: String,
+    /// underpaid or overpaid once the watcher sees a funding mismatch
+    pub o_funding: String,
     pub o_ship_date: i64,
     pub o_hash: String,
     pub o_msig_prepare: String,
     pub o_msig_make: String,
-    pub o_msig_kex_1: String,
-    pub o_msig_kex_2: String,
-    pub o_msig_kex_3: String,
+    pub o_msig_kex: Vec<String>,
+    pub o_msig_export: String,
+    /// payout txset being signed, updated as each party signs
+    pub o_msig_txset: String,
+    /// roles that have signed `o_msig_txset`
+    pub o_signers: Vec<String>,
+    /// release or refund, what `o_msig_txset` pays out
+    pub o_payout: String,
+    /// tx hash of the submitted payout
+    pub o_payout_hash: String,
+    /// customer address for refunds
+    pub o_refund_address: String,
     pub o_subaddress: String,
     pub o_status: String,
     pub o_quantity: i64,
-    pub o_vend_kex_1: String,
-    pub o_vend_kex_2: String,
-    pub o_vend_kex_3: String,
+    pub o_vend_kex: Vec<String>,
+    pub o_vend_msig_export: String,
     pub o_vend_msig_info: String,
-    pub o_vend_msig_txset: String,
+    pub o_variant: String,
+    pub o_shipping: String,
+    /// amount the order wallet must be funded with
+    pub o_amount: i64,
+    /// roles that agreed to cancel a funded order
+    pub o_cancel: Vec<String>,
+    /// fiat currency the product was priced in, empty for xmr
+    pub o_currency: String,
+    /// cents of `o_currency` per xmr locked at order time
+    pub o_rate: i64,
+    /// piconero received by the order wallet
+    pub o_received: i64,
+    /// status transitions, oldest first
+    pub o_history: Vec<StatusChange>,
 }
 
 impl Default for Order {
@@ -128,31 +326,167 @@ impl Default for Order {
             v_id: utils::empty_string(),
             o_xmr_address: utils::empty_string(),
             o_cust_msig_info: utils::empty_string(),
-            o_cust_msig_txset: utils::empty_string(),
-            o_cust_kex_1: utils::empty_string(),
-            o_cust_kex_2: utils::empty_string(),
-            o_cust_kex_3: utils::empty_string(),
+            o_cust_kex: Vec::new(),
+            o_cust_msig_export: utils::empty_string(),
             o_date: 0,
             o_deliver_date: 0,
+            o_fee: 0,
+            o_fee_address: utils::empty_string(),
+            o_funding: utils::empty_string(),
             o_ship_date: 0,
             o_hash: utils::empty_string(),
             o_msig_prepare: utils::empty_string(),
             o_msig_make: utils::empty_string(),
-            o_msig_kex_1: utils::empty_string(),
-            o_msig_kex_2: utils::empty_string(),
-            o_msig_kex_3: utils::empty_string(),
+            o_msig_kex: Vec::new(),
+            o_msig_export: utils::empty_string(),
+            o_msig_txset: utils::empty_string(),
+            o_signers: Vec::new(),
+            o_payout: utils::empty_string(),
+            o_payout_hash: utils::empty_string(),
+            o_refund_address: utils::empty_string(),
             o_subaddress: utils::empty_string(),
             o_status: utils::empty_string(),
             o_quantity: 0,
-            o_vend_kex_1: utils::empty_string(),
-            o_vend_kex_2: utils::empty_string(),
-            o_vend_kex_3: utils::empty_string(),
+            o_vend_kex: Vec::new(),
+            o_vend_msig_export: utils::empty_string(),
             o_vend_msig_info: utils::empty_string(),
-            o_vend_msig_txset: utils::empty_string(),
+            o_variant: utils::empty_string(),
+            o_shipping: utils::empty_string(),
+            o_amount: 0,
+            o_cancel: Vec::new(),
+            o_currency: utils::empty_string(),
+            o_rate: 0,
+            o_received: 0,
+            o_history: Vec::new(),
         }
     }
 }
 
+/// Split a comma separated list field
+pub fn split_list(v: String) -> Vec<String> {
+    v.split(',').filter(|i| !i.is_empty()).map(String::from).collect()
+}
+
+impl Order {
+    pub fn to_db(o: &Order) -> String {
+        let history: Vec<String> = o.o_history.iter().map(StatusChange::to_db).collect();
+        format!("{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            o.c_id, o.o_amount, o.o_cancel.join(","), o.o_currency, o.o_cust_kex.join(","), o.o_cust_msig_export,
+            o.o_cust_msig_info, o.o_date, o.o_deliver_date, o.o_fee, o.o_fee_address, o.o_funding, o.o_hash, history.join(","),
+            o.o_msig_export, o.o_msig_kex.join(","), o.o_msig_make, o.o_msig_prepare,
+            o.o_msig_txset, o.o_payout, o.o_payout_hash, o.o_quantity, o.o_rate,
+            o.o_received, o.o_refund_address, o.o_ship_date, o.o_shipping,
+            o.o_signers.join(","), o.o_status, o.o_subaddress, o.o_variant, o.o_vend_kex.join(","),
+            o.o_vend_msig_export, o.o_vend_msig_info, o.o_xmr_address, o.p_id, o.v_id)
+    }
+    pub fn from_db(k: String, v: String) -> Order {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let c_id = v.remove(0);
+        let o_amount = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_cancel = split_list(v.remove(0));
+        let o_currency = v.remove(0);
+        let o_cust_kex = split_list(v.remove(0));
+        let o_cust_msig_export = v.remove(0);
+        let o_cust_msig_info = v.remove(0);
+        let o_date = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_deliver_date = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_fee = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_fee_address = v.remove(0);
+        let o_funding = v.remove(0);
+        let o_hash = v.remove(0);
+        let o_history: Vec<StatusChange> = split_list(v.remove(0))
+            .iter()
+            .map(|c| StatusChange::from_db(c))
+            .collect();
+        let o_msig_export = v.remove(0);
+        let o_msig_kex = split_list(v.remove(0));
+        let o_msig_make = v.remove(0);
+        let o_msig_prepare = v.remove(0);
+        let o_msig_txset = v.remove(0);
+        let o_payout = v.remove(0);
+        let o_payout_hash = v.remove(0);
+        let o_quantity = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_rate = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_received = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_refund_address = v.remove(0);
+        let o_ship_date = v.remove(0).parse::<i64>().unwrap_or(0);
+        let o_shipping = v.remove(0);
+        let o_signers = split_list(v.remove(0));
+        let o_status = v.remove(0);
+        let o_subaddress = v.remove(0);
+        let o_variant = v.remove(0);
+        let o_vend_kex = split_list(v.remove(0));
+        let o_vend_msig_export = v.remove(0);
+        let o_vend_msig_info = v.remove(0);
+        let o_xmr_address = v.remove(0);
+        let p_id = v.remove(0);
+        let v_id = v.remove(0);
+        Order {
+            orid: k,
+            c_id,
+            p_id,
+            v_id,
+            o_xmr_address,
+            o_cust_msig_info,
+            o_cust_kex,
+            o_cust_msig_export,
+            o_date,
+            o_deliver_date,
+            o_fee,
+            o_fee_address,
+            o_funding,
+            o_ship_date,
+            o_hash,
+            o_msig_prepare,
+            o_msig_make,
+            o_msig_kex,
+            o_msig_export,
+            o_msig_txset,
+            o_signers,
+            o_payout,
+            o_payout_hash,
+            o_refund_address,
+            o_subaddress,
+            o_status,
+            o_quantity,
+            o_vend_kex,
+            o_vend_msig_export,
+            o_vend_msig_info,
+            o_variant,
+            o_shipping,
+            o_amount,
+            o_cancel,
+            o_currency,
+            o_rate,
+            o_received,
+            o_history,
+        }
+    }
+}
+
+/// Order status transition, stored inside the order record
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub status: String,
+    /// customer, vendor, mediator or system
+    pub role: String,
+    pub timestamp: i64,
+}
+
+impl StatusChange {
+    pub fn to_db(c: &StatusChange) -> String {
+        format!("{};{};{}", c.status, c.role, c.timestamp)
+    }
+    pub fn from_db(r: &str) -> StatusChange {
+        let mut v: Vec<String> = r.split(';').map(String::from).collect();
+        let status = v.remove(0);
+        let role = v.remove(0);
+        let timestamp = v.remove(0).parse::<i64>().unwrap_or(0);
+        StatusChange { status, role, timestamp }
+    }
+}
+
 #[derive(Debug)]
 pub struct Authorization {
     pub aid: String,
@@ -218,11 +552,61 @@ impl Authorization {
 }
 
 #[derive(Debug)]
+pub struct Mediator {
+    pub mid: String,
+    pub m_xmr_address: String,
+    pub m_name: String,
+    pub m_pgp: String,
+    pub active: bool,
+    pub created: i64,
+}
+
+impl Default for Mediator {
+    fn default() -> Self {
+        Mediator {
+            mid: utils::empty_string(),
+            m_xmr_address: utils::empty_string(),
+            m_name: utils::empty_string(),
+            m_pgp: utils::empty_string(),
+            active: false,
+            created: 0,
+        }
+    }
+}
+
+impl Mediator {
+    pub fn to_db(m: &Mediator) -> String {
+        format!("{}:{}:{}:{}:{}",
+            m.active, m.created, hex::encode(&m.m_name), hex::encode(&m.m_pgp), m.m_xmr_address)
+    }
+    pub fn from_db(k: String, v: String) -> Mediator {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let active = v.remove(0).parse::<bool>().unwrap_or(false);
+        let created = v.remove(0).parse::<i64>().unwrap_or(0);
+        let m_name = utils::decode_hex(&v.remove(0));
+        let m_pgp = utils::decode_hex(&v.remove(0));
+        let m_xmr_address = v.remove(0);
+        Mediator { mid: k, m_xmr_address, m_name, m_pgp, active, created }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Dispute {
     pub did: String,
     pub created: i64,
     pub orid: String,
-    pub tx_set: String,
+    /// role of the party that opened the dispute
+    pub opened_by: String,
+    pub reason: String,
+    /// assigned mediator, empty while the dispute is queued
+    pub m_id: String,
+    pub status: String,
+    /// release, refund or split once resolved
+    pub resolution: String,
+    pub resolved: i64,
+    /// vendor's percentage of a split resolution, the customer is refunded the rest
+    pub split: u8,
 }
 
 impl Default for Dispute {
@@ -231,7 +615,237 @@ impl Default for Dispute {
             did: utils::empty_string(),
             created: 0,
             orid: utils::empty_string(),
-            tx_set: utils::empty_string(),
+            opened_by: utils::empty_string(),
+            reason: utils::empty_string(),
+            m_id: utils::empty_string(),
+            status: utils::empty_string(),
+            resolution: utils::empty_string(),
+            resolved: 0,
+            split: 0,
+        }
+    }
+}
+
+impl Dispute {
+    pub fn to_db(d: &Dispute) -> String {
+        format!("{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            d.created, d.m_id, d.opened_by, d.orid, hex::encode(&d.reason), d.resolution,
+            d.resolved, d.split, d.status)
+    }
+    pub fn from_db(k: String, v: String) -> Dispute {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let created = v.remove(0).parse::<i64>().unwrap_or(0);
+        let m_id = v.remove(0);
+        let opened_by = v.remove(0);
+        let orid = v.remove(0);
+        let reason = utils::decode_hex(&v.remove(0));
+        let resolution = v.remove(0);
+        let resolved = v.remove(0).parse::<i64>().unwrap_or(0);
+        let split = v.remove(0).parse::<u8>().unwrap_or(0);
+        let status = v.remove(0);
+        Dispute { did: k, created, orid, opened_by, reason, m_id, status, resolution, resolved, split }
+    }
+}
+
+/// Commission override for a product category, the command line sets the default
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commission {
+    pub category: String,
+    /// percent or flat
+    pub kind: String,
+    /// basis points for a percentage, piconero for a flat fee
+    pub value: i64,
+}
+
+impl Default for Commission {
+    fn default() -> Self {
+        Commission {
+            category: utils::empty_string(),
+            kind: utils::empty_string(),
+            value: 0,
+        }
+    }
+}
+
+impl Commission {
+    pub fn to_db(c: &Commission) -> String {
+        format!("{}:{}", c.kind, c.value)
+    }
+    pub fn from_db(category: String, v: String) -> Commission {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let kind = v.remove(0);
+        let value = v.remove(0).parse::<i64>().unwrap_or(0);
+        Commission { category, kind, value }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Evidence {
+    pub eid: String,
+    pub orid: String,
+    /// role of the submitter
+    pub role: String,
+    /// statement or file
+    pub kind: String,
+    pub created: i64,
+    /// armored pgp statement, or the hash of a stored file
+    pub data: String,
+}
+
+impl Default for Evidence {
+    fn default() -> Self {
+        Evidence {
+            eid: utils::empty_string(),
+            orid: utils::empty_string(),
+            role: utils::empty_string(),
+            kind: utils::empty_string(),
+            created: 0,
+            data: utils::empty_string(),
+        }
+    }
+}
+
+impl Evidence {
+    pub fn to_db(e: &Evidence) -> String {
+        format!("{}:{}:{}:{}:{}",
+            e.created, hex::encode(&e.data), e.kind, e.orid, e.role)
+    }
+    pub fn from_db(k: String, v: String) -> Evidence {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let created = v.remove(0).parse::<i64>().unwrap_or(0);
+        let data = utils::decode_hex(&v.remove(0));
+        let kind = v.remove(0);
+        let orid = v.remove(0);
+        let role = v.remove(0);
+        Evidence { eid: k, orid, role, kind, created, data }
+    }
+}
+
+#[derive(Debug)]
+pub struct Bond {
+    pub bid: String,
+    pub vid: String,
+    pub amount: u128,
+    pub address: String,
+    pub address_index: u32,
+    pub created: i64,
+    pub status: String,
+    pub tx_hash: String,
+}
+
+impl Default for Bond {
+    fn default() -> Self {
+        Bond {
+            bid: utils::empty_string(),
+            vid: utils::empty_string(),
+            amount: 0,
+            address: utils::empty_string(),
+            address_index: 0,
+            created: 0,
+            status: utils::empty_string(),
+            tx_hash: utils::empty_string(),
+        }
+    }
+}
+
+impl Bond {
+    pub fn to_db(b: &Bond) -> String {
+        format!("{}:{}:{}:{}:{}:{}:{}",
+            b.vid, b.amount, b.address, b.address_index, b.created, b.status, b.tx_hash)
+    }
+    pub fn from_db(k: String, v: String) -> Bond {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let vid = v.remove(0);
+        let amount = v.remove(0).parse::<u128>().unwrap_or(0);
+        let address = v.remove(0);
+        let address_index = v.remove(0).parse::<u32>().unwrap_or(0);
+        let created = v.remove(0).parse::<i64>().unwrap_or(0);
+        let status = v.remove(0);
+        let tx_hash = v.remove(0);
+        Bond { bid: k, vid, amount, address, address_index, created, status, tx_hash }
+    }
+    pub fn update_status(b: Bond, status: String, tx_hash: String) -> Bond {
+        Bond {
+            bid: b.bid,
+            vid: b.vid,
+            amount: b.amount,
+            address: b.address,
+            address_index: b.address_index,
+            created: b.created,
+            status,
+            tx_hash,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Feedback {
+    pub fid: String,
+    pub orid: String,
+    pub c_id: String,
+    pub p_id: String,
+    pub v_id: String,
+    pub rating: u8,
+    pub comment: String,
+    pub created: i64,
+    pub reply: String,
+    pub reply_date: i64,
+}
+
+impl Default for Feedback {
+    fn default() -> Self {
+        Feedback {
+            fid: utils::empty_string(),
+            orid: utils::empty_string(),
+            c_id: utils::empty_string(),
+            p_id: utils::empty_string(),
+            v_id: utils::empty_string(),
+            rating: 0,
+            comment: utils::empty_string(),
+            created: 0,
+            reply: utils::empty_string(),
+            reply_date: 0,
+        }
+    }
+}
+
+impl Feedback {
+    /// comment and reply are free text so they are stored hex encoded
+    pub fn to_db(f: &Feedback) -> String {
+        format!("{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            f.c_id, hex::encode(&f.comment), f.created, f.orid, f.p_id,
+            f.rating, hex::encode(&f.reply), f.reply_date, f.v_id)
+    }
+    pub fn from_db(k: String, v: String) -> Feedback {
+        let values = v.split(':');
+        let mut v: Vec<String> = values.map(String::from).collect();
+        let c_id = v.remove(0);
+        let comment = utils::decode_hex(&v.remove(0));
+        let created = v.remove(0).parse::<i64>().unwrap_or(0);
+        let orid = v.remove(0);
+        let p_id = v.remove(0);
+        let rating = v.remove(0).parse::<u8>().unwrap_or(0);
+        let reply = utils::decode_hex(&v.remove(0));
+        let reply_date = v.remove(0).parse::<i64>().unwrap_or(0);
+        let v_id = v.remove(0);
+        Feedback { fid: k, orid, c_id, p_id, v_id, rating, comment, created, reply, reply_date }
+    }
+    pub fn update_reply(f: Feedback, reply: String, reply_date: i64) -> Feedback {
+        Feedback {
+            fid: f.fid,
+            orid: f.orid,
+            c_id: f.c_id,
+            p_id: f.p_id,
+            v_id: f.v_id,
+            rating: f.rating,
+            comment: f.comment,
+            created: f.created,
+            reply,
+            reply_date,
         }
     }
 }
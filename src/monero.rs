@@ -1,28 +1,38 @@
-use crate::{args, reqres, utils};
-use clap::Parser;
-use diqwest::WithDigestAuth;
-use log::{debug, error, info};
-use std::process::Command;
-
-struct RpcLogin {
-    username: String,
-    credential: String,
-}
+use crate::{reqres, rpc, utils};
+use log::info;
 
 enum RpcFields {
     Balance,
     Create,
     Close,
+    CreateAddress,
+    DescribeTransfer,
+    ExchangeKeys,
     Export,
-    Finalize,
+    ExportKeyImages,
+    GetAddress,
+    GetHeight,
+    GetTransferByTxid,
+    GetTransfers,
     GetVersion,
-    Id,
     Import,
-    JsonRpcVersion,
+    ImportKeyImages,
+    IncomingTransfers,
+    IsMultisig,
+    LabelAddress,
     Make,
+    MakeUri,
     Open,
+    ParseUri,
     Prepare,
+    Refresh,
     SignMultisig,
+    StopWallet,
+    SubmitMultisig,
+    SweepAll,
+    Transfer,
+    TransferSplit,
+    ValidateAddress,
     Verify,
 }
 
@@ -32,441 +42,489 @@ impl RpcFields {
             RpcFields::Balance => String::from("get_balance"),
             RpcFields::Create => String::from("create_wallet"),
             RpcFields::Close => String::from("close_wallet"),
+            RpcFields::CreateAddress => String::from("create_address"),
+            RpcFields::DescribeTransfer => String::from("describe_transfer"),
+            RpcFields::ExchangeKeys => String::from("exchange_multisig_keys"),
             RpcFields::Export => String::from("export_multisig_info"),
-            RpcFields::Finalize => String::from("finalize_multisig"),
+            RpcFields::ExportKeyImages => String::from("export_key_images"),
+            RpcFields::GetAddress => String::from("get_address"),
+            RpcFields::GetHeight => String::from("get_height"),
+            RpcFields::GetTransferByTxid => String::from("get_transfer_by_txid"),
+            RpcFields::GetTransfers => String::from("get_transfers"),
             RpcFields::GetVersion => String::from("get_version"),
-            RpcFields::Id => String::from("0"),
             RpcFields::Import => String::from("import_multisig_info"),
-            RpcFields::JsonRpcVersion => String::from("2.0"),
+            RpcFields::ImportKeyImages => String::from("import_key_images"),
+            RpcFields::IncomingTransfers => String::from("incoming_transfers"),
+            RpcFields::IsMultisig => String::from("is_multisig"),
+            RpcFields::LabelAddress => String::from("label_address"),
             RpcFields::Make => String::from("make_multisig"),
+            RpcFields::MakeUri => String::from("make_uri"),
             RpcFields::Open => String::from("open_wallet"),
+            RpcFields::ParseUri => String::from("parse_uri"),
             RpcFields::Prepare => String::from("prepare_multisig"),
+            RpcFields::Refresh => String::from("refresh"),
             RpcFields::SignMultisig => String::from("sign_multisig"),
+            RpcFields::StopWallet => String::from("stop_wallet"),
+            RpcFields::SubmitMultisig => String::from("submit_multisig"),
+            RpcFields::SweepAll => String::from("sweep_all"),
+            RpcFields::Transfer => String::from("transfer"),
+            RpcFields::TransferSplit => String::from("transfer_split"),
+            RpcFields::ValidateAddress => String::from("validate_address"),
             RpcFields::Verify => String::from("verify"),
         }
     }
 }
 
-pub async fn start_rpc() {
-    info!("starting monero-wallet-rpc");
-    let port = get_rpc_port();
-    let login = get_rpc_creds();
-    let creds = format!("{}:{}", login.username, login.credential);
-    let pass = get_rpc_password();
-    let daemon = get_rpc_daemon();
-    // TODO: cmd line args 
-    let args = ["--rpc-bind-port", &port,
-        "--wallet-dir", "wallet", "--rpc-login", &creds,
-        "--password", &pass, "--daemon-address", &daemon];
-    let env = utils::get_release_env();
-    if env == utils::ReleaseEnvironment::Development {
-        let output = Command::new("./monero-wallet-rpc")
-        .args(args)
-        .arg("--stagenet")
-        .spawn()
-        .expect("monero-wallet-rpc failed to start");
-        debug!("{:?}", output.stdout);
-    }  else {
-        let output = Command::new("./monero-wallet-rpc")
-        .args(args)
-        .spawn()
-        .expect("monero-wallet-rpc failed to start");
-        debug!("{:?}", output.stdout);
-    }
+/// Performs rpc 'get_version' method
+pub async fn get_version() -> Result<reqres::XmrRpcVersionResponse, rpc::RpcError> {
+    let method = RpcFields::GetVersion;
+    rpc::client()
+        .call(&method.value(), rpc::NoParams {})
+        .await
+        .map(|result| reqres::XmrRpcVersionResponse { result })
 }
 
-fn get_rpc_port() -> String {
-    let args = args::Args::parse();
-    let rpc = String::from(args.monero_rpc_host);
-    let values = rpc.split(":");
-    let mut v: Vec<String> = values.map(|s| String::from(s)).collect();
-    let  port = v.remove(2);
-    debug!("monero-wallet-rpc port: {}", port);
-    port
+/// Check wallet-rpc answers without logging failures, used while it
+/// starts up
+pub async fn is_ready() -> bool {
+    let res: Result<reqres::XmrRpcVersionResult, rpc::RpcError> =
+        rpc::client().try_call(&RpcFields::GetVersion.value(), &rpc::NoParams {}).await;
+    res.map(|r| r.version > 0).unwrap_or(false)
 }
 
-/// Get monero rpc host from command line argument
-fn get_rpc_host() -> String {
-    let args = args::Args::parse();
-    let rpc = String::from(args.monero_rpc_host);
-    format!("{}/json_rpc", rpc)
+/// Performs the xmr rpc 'stop_wallet' method, wallet-rpc saves the open
+/// wallet and exits
+pub async fn stop_wallet() -> Result<(), rpc::RpcError> {
+    let res: Result<rpc::EmptyResult, rpc::RpcError> =
+        rpc::client().call(&RpcFields::StopWallet.value(), rpc::NoParams {}).await;
+    res.map(|_| ())
 }
 
-/// Get monero rpc host from command line argument
-fn get_rpc_password() -> String {
-    let args = args::Args::parse();
-    String::from(args.monero_rpc_password)
+/// Performs the xmr rpc 'verify' method, true when the address signed the data
+pub async fn verify_signature(
+    address: String,
+    data: String,
+    signature: String,
+) -> Result<bool, rpc::RpcError> {
+    info!("signature verification in progress");
+    let params = reqres::XmrRpcVerifyParams {
+        address,
+        data,
+        signature,
+    };
+    let res: Result<reqres::XmrRpcVerifyResult, rpc::RpcError> =
+        rpc::client().call(&RpcFields::Verify.value(), params).await;
+    res.map(|r| r.good)
 }
 
-/// Get monero rpc host from command line argument
-fn get_rpc_creds() -> RpcLogin {
-    let args = args::Args::parse();
-    let username = String::from(args.monero_rpc_username);
-    let credential = String::from(args.monero_rpc_cred);
-    RpcLogin { username, credential }
+/// Open, create and close return an empty result
+async fn wallet_call(method: RpcFields, filename: String) -> Result<(), rpc::RpcError> {
+    let params = reqres::XmrRpcWalletParams {
+        filename,
+        language: String::from("English"),
+    };
+    let res: Result<rpc::EmptyResult, rpc::RpcError> = rpc::client().call(&method.value(), params).await;
+    res.map(|_| ())
 }
 
-fn get_rpc_daemon() -> String {
-    let args = args::Args::parse();
-    String::from(args.monero_rpc_daemon)
+/// Performs the xmr rpc 'create_wallet' method
+pub async fn create_wallet(filename: String) -> Result<(), rpc::RpcError> {
+    info!("creating mulisig wallet for order {}", &filename);
+    wallet_call(RpcFields::Create, filename).await
 }
 
-/// Performs rpc 'get_version' method
-pub async fn get_version() -> reqres::XmrRpcVersionResponse {
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let req = reqres::XmrRpcRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::GetVersion.value(),
-    };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcVersionResponse>().await;
-            debug!("get version response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_e) => Default::default(),
-    }
+/// Performs the xmr rpc 'open_wallet' method
+pub async fn open_wallet(filename: String) -> Result<(), rpc::RpcError> {
+    info!("opening wallet for order {}", &filename);
+    wallet_call(RpcFields::Open, filename).await
 }
 
-/// Helper function for checking xmr rpc online during app startup
-pub async fn check_rpc_connection() -> () {
-    let res: reqres::XmrRpcVersionResponse = get_version().await;
-    if res.result.version == 0 {
-        error!("failed to connect to monero-wallet-rpc");
-    }
+/// Performs the xmr rpc 'close_wallet' method
+pub async fn close_wallet(filename: String) -> Result<(), rpc::RpcError> {
+    info!("closing wallet for order {}", &filename);
+    wallet_call(RpcFields::Close, filename).await
 }
 
-/// Performs the xmr rpc 'verify' method
-pub async fn verify_signature(address: String, data: String, signature: String) -> String {
-    info!("signature verification in progress");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let params = reqres::XmrRpcVerifyParams {
-        address,
-        data,
-        signature,
+/// Performs the xmr rpc 'get_balance' method
+pub async fn get_balance(address_indices: Vec<u32>) -> Result<reqres::XmrRpcBalanceResponse, rpc::RpcError> {
+    info!("fetching wallet balance");
+    let method = RpcFields::Balance;
+    let params: reqres::XmrRpcBalanceParams = reqres::XmrRpcBalanceParams {
+        account_index: 0, address_indices, all_accounts: false, strict: false,
     };
-    let req = reqres::XmrRpcVerifyRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Verify.value(),
-        params,
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcBalanceResponse { result })
+}
+/// Performs the xmr rpc 'get_transfers' method for incoming and pool transfers
+pub async fn get_transfers() -> Result<reqres::XmrRpcGetTransfersResponse, rpc::RpcError> {
+    info!("fetching incoming transfers");
+    let method = RpcFields::GetTransfers;
+    let params = reqres::XmrRpcGetTransfersParams {
+        incoming: true,
+        pool: true,
+        account_index: 0,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcVerifyResponse>().await;
-            debug!("verify response: {:?}", res);
-            match res {
-                Ok(res) => {
-                    if res.result.good {
-                        req.params.address
-                    } else {
-                        utils::ApplicationErrors::LoginError.value()
-                    }
-                }
-                _ => utils::ApplicationErrors::LoginError.value(),
-            }
-        }
-        Err(_e) => utils::ApplicationErrors::LoginError.value(),
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcGetTransfersResponse { result })
 }
 
-/// Performs the xmr rpc 'create_wallet' method
-pub async fn create_wallet(filename: String) -> bool {
-    info!("creating mulisig wallet for order {}", &filename);
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let params = reqres::XmrRpcWalletParams {
-        filename,
-        language: String::from("English"),
+/// Performs the xmr rpc 'create_address' method
+pub async fn create_address(label: String) -> Result<reqres::XmrRpcCreateAddressResponse, rpc::RpcError> {
+    info!("creating subaddress");
+    let method = RpcFields::CreateAddress;
+    let params = reqres::XmrRpcCreateAddressParams {
+        account_index: 0,
+        label,
     };
-    let req = reqres::XmrRpcCreateRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Create.value(),
-        params,
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcCreateAddressResponse { result })
+}
+
+/// Performs the xmr rpc 'sweep_all' method
+pub async fn sweep_all(
+    address: String,
+    subaddr_indices: Vec<u32>,
+) -> Result<reqres::XmrRpcSweepAllResponse, rpc::RpcError> {
+    info!("sweeping subaddresses {:?}", &subaddr_indices);
+    let method = RpcFields::SweepAll;
+    let params = reqres::XmrRpcSweepAllParams {
+        address,
+        account_index: 0,
+        subaddr_indices,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            // The result from wallet creation is empty
-            let res = response.text().await;
-            debug!("create response: {:?}", res);
-            match res {
-                Ok(_) => true,
-                _ => false,
-            }
-        }
-        Err(_) => false
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcSweepAllResponse { result })
 }
 
-/// Performs the xmr rpc 'open_wallet' method
-pub async fn open_wallet(filename: String) -> bool {
-    info!("opening wallet for order {}", &filename);
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let params = reqres::XmrRpcWalletParams {
-        filename,
-        language: String::from("English"),
+/// Performs the xmr rpc 'get_address' method, an empty list returns
+/// every subaddress of the primary account
+pub async fn get_address(address_index: Vec<u32>) -> Result<reqres::XmrRpcGetAddressResponse, rpc::RpcError> {
+    info!("fetching wallet addresses");
+    let method = RpcFields::GetAddress;
+    let params = reqres::XmrRpcGetAddressParams {
+        account_index: 0,
+        address_index,
     };
-    let req = reqres::XmrRpcCreateRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Open.value(),
-        params,
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcGetAddressResponse { result })
+}
+
+/// Performs the xmr rpc 'label_address' method
+pub async fn label_address(minor: u32, label: String) -> Result<(), rpc::RpcError> {
+    info!("labeling subaddress {}", minor);
+    let params = reqres::XmrRpcLabelAddressParams {
+        index: reqres::SubaddressIndex { major: 0, minor },
+        label,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            // The result from wallet operation is empty
-            let res = response.text().await;
-            debug!("open response: {:?}", res);
-            match res {
-                Ok(_) => true,
-                _ => false,
-            }
-        }
-        Err(_) => false
-    }
+    let res: Result<rpc::EmptyResult, rpc::RpcError> =
+        rpc::client().call(&RpcFields::LabelAddress.value(), params).await;
+    res.map(|_| ())
 }
 
-/// Performs the xmr rpc 'close_wallet' method
-pub async fn close_wallet(filename: String) -> bool {
-    info!("closing wallet for order {}", &filename);
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let params = reqres::XmrRpcWalletParams {
-        filename,
-        language: String::from("English"),
+/// Performs the xmr rpc 'get_transfer_by_txid' method
+pub async fn get_transfer_by_txid(
+    txid: String,
+) -> Result<reqres::XmrRpcGetTransferByTxidResponse, rpc::RpcError> {
+    info!("fetching transfer {}", &txid);
+    let method = RpcFields::GetTransferByTxid;
+    let params = reqres::XmrRpcGetTransferByTxidParams {
+        txid,
+        account_index: 0,
     };
-    let req = reqres::XmrRpcCreateRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Close.value(),
-        params,
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcGetTransferByTxidResponse { result })
+}
+
+/// Performs the xmr rpc 'incoming_transfers' method. The transfer type is
+/// one of `all`, `available` or `unavailable`.
+pub async fn incoming_transfers(
+    transfer_type: String,
+    subaddr_indices: Vec<u32>,
+) -> Result<reqres::XmrRpcIncomingTransfersResponse, rpc::RpcError> {
+    info!("fetching {} incoming transfers", &transfer_type);
+    let method = RpcFields::IncomingTransfers;
+    let params = reqres::XmrRpcIncomingTransfersParams {
+        transfer_type,
+        account_index: 0,
+        subaddr_indices,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            // The result from wallet operation is empty
-            let res = response.text().await;
-            debug!("close response: {:?}", res);
-            match res {
-                Ok(_) => true,
-                _ => false,
-            }
-        }
-        Err(_) => false
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcIncomingTransfersResponse { result })
 }
 
-/// Performs the xmr rpc 'get_balance' method
-pub async fn get_balance() -> reqres::XmrRpcBalanceResponse {
-    info!("fetching wallet balance");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let params: reqres::XmrRpcBalanceParams = reqres::XmrRpcBalanceParams { 
-        account_index: 0, address_indices: vec![0], all_accounts: false, strict: false, 
+/// Performs the xmr rpc 'transfer_split' method, for payments too large
+/// to fit in a single transaction
+pub async fn transfer_split(
+    destinations: Vec<reqres::Destination>,
+) -> Result<reqres::XmrRpcTransferSplitResponse, rpc::RpcError> {
+    info!("split transfer to {} destinations", destinations.len());
+    let method = RpcFields::TransferSplit;
+    let params = reqres::XmrRpcTransferSplitParams {
+        destinations,
+        account_index: 0,
     };
-    let req = reqres::XmrRpcBalanceRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Balance.value(),
-        params,
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcTransferSplitResponse { result })
+}
+
+/// Performs the xmr rpc 'export_key_images' method
+pub async fn export_key_images(all: bool) -> Result<reqres::XmrRpcExportKeyImagesResponse, rpc::RpcError> {
+    info!("exporting key images");
+    let method = RpcFields::ExportKeyImages;
+    let params = reqres::XmrRpcExportKeyImagesParams { all };
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcExportKeyImagesResponse { result })
+}
+
+/// Performs the xmr rpc 'import_key_images' method
+pub async fn import_key_images(
+    signed_key_images: Vec<reqres::SignedKeyImage>,
+) -> Result<reqres::XmrRpcImportKeyImagesResponse, rpc::RpcError> {
+    info!("importing {} key images", signed_key_images.len());
+    let method = RpcFields::ImportKeyImages;
+    let params = reqres::XmrRpcImportKeyImagesParams { signed_key_images };
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcImportKeyImagesResponse { result })
+}
+
+/// Performs the xmr rpc 'refresh' method, from the last scanned
+/// height unless a start height is given
+pub async fn refresh(start_height: Option<u64>) -> Result<reqres::XmrRpcRefreshResponse, rpc::RpcError> {
+    info!("refreshing wallet");
+    let method = RpcFields::Refresh;
+    let params = reqres::XmrRpcRefreshParams { start_height };
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcRefreshResponse { result })
+}
+
+/// Performs the xmr rpc 'get_height' method
+pub async fn get_height() -> Result<reqres::XmrRpcGetHeightResponse, rpc::RpcError> {
+    info!("fetching wallet height");
+    let method = RpcFields::GetHeight;
+    rpc::client()
+        .call(&method.value(), rpc::NoParams {})
+        .await
+        .map(|result| reqres::XmrRpcGetHeightResponse { result })
+}
+
+/// Performs the xmr rpc 'validate_address' method. Only addresses on the
+/// network the wallet runs on are accepted.
+pub async fn validate_address(
+    address: String,
+) -> Result<reqres::XmrRpcValidateAddressResponse, rpc::RpcError> {
+    info!("validating address");
+    let method = RpcFields::ValidateAddress;
+    let params = reqres::XmrRpcValidateAddressParams {
+        address,
+        any_net_type: false,
+        allow_openalias: false,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcBalanceResponse>().await;
-            debug!("balance response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcValidateAddressResponse { result })
+}
+
+/// Performs the xmr rpc 'make_uri' method
+pub async fn make_uri(payment: reqres::PaymentUri) -> Result<reqres::XmrRpcMakeUriResponse, rpc::RpcError> {
+    info!("making payment uri");
+    let method = RpcFields::MakeUri;
+    rpc::client()
+        .call(&method.value(), payment)
+        .await
+        .map(|result| reqres::XmrRpcMakeUriResponse { result })
+}
+
+/// Performs the xmr rpc 'parse_uri' method
+pub async fn parse_uri(uri: String) -> Result<reqres::XmrRpcParseUriResponse, rpc::RpcError> {
+    info!("parsing payment uri");
+    let method = RpcFields::ParseUri;
+    let params = reqres::XmrRpcParseUriParams { uri };
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcParseUriResponse { result })
 }
 // START Multisig
 
 /// Performs the xmr rpc 'prepare_multisig' method
-pub async fn prepare_wallet() -> reqres::XmrRpcPrepareResponse {
+pub async fn prepare_wallet() -> Result<reqres::XmrRpcPrepareResponse, rpc::RpcError> {
     info!("prepare msig wallet");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let req = reqres::XmrRpcRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Prepare.value(),
-    };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcPrepareResponse>().await;
-            debug!("prepare response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    let method = RpcFields::Prepare;
+    rpc::client()
+        .call(&method.value(), rpc::NoParams {})
+        .await
+        .map(|result| reqres::XmrRpcPrepareResponse { result })
 }
 
 /// Performs the xmr rpc 'make_multisig' method
-pub async fn make_wallet(info: Vec<String>) -> reqres::XmrRpcMakeResponse {
+pub async fn make_wallet(
+    info: Vec<String>,
+    threshold: u32,
+) -> Result<reqres::XmrRpcMakeResponse, rpc::RpcError> {
     info!("make msig wallet");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
+    let method = RpcFields::Make;
     let params = reqres::XmrRpcMakeParams {
         multisig_info: info,
-        threshold: 2,
-    };
-    let req = reqres::XmrRpcMakeRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Make.value(),
-        params,
+        threshold,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcMakeResponse>().await;
-            debug!("make response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcMakeResponse { result })
 }
 
-/// Performs the xmr rpc 'finalize_multisig' method
-pub async fn finalize_wallet(info: Vec<String>) -> reqres::XmrRpcFinalizeResponse {
-    info!("finalize msig wallet");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let params = reqres::XmrRpcFinalizeParams {
+/// Performs the xmr rpc 'exchange_multisig_keys' method, one call per
+/// key exchange round after make_multisig
+pub async fn exchange_multisig_keys(
+    info: Vec<String>,
+) -> Result<reqres::XmrRpcExchangeKeysResponse, rpc::RpcError> {
+    info!("exchange msig keys");
+    let method = RpcFields::ExchangeKeys;
+    let params = reqres::XmrRpcExchangeKeysParams {
         multisig_info: info,
+        password: utils::empty_string(),
     };
-    let req = reqres::XmrRpcFinalizeRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Finalize.value(),
-        params,
-    };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcFinalizeResponse>().await;
-            debug!("finalize response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcExchangeKeysResponse { result })
+}
+
+/// Performs the xmr rpc 'is_multisig' method
+pub async fn is_multisig() -> Result<reqres::XmrRpcIsMultisigResponse, rpc::RpcError> {
+    info!("check msig wallet status");
+    let method = RpcFields::IsMultisig;
+    rpc::client()
+        .call(&method.value(), rpc::NoParams {})
+        .await
+        .map(|result| reqres::XmrRpcIsMultisigResponse { result })
 }
 
 /// Performs the xmr rpc 'export_multisig_info' method
-pub async fn export_multisig_info() -> reqres::XmrRpcExportResponse {
+pub async fn export_multisig_info() -> Result<reqres::XmrRpcExportResponse, rpc::RpcError> {
     info!("export msig info");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
-    let req = reqres::XmrRpcRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Export.value(),
-    };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcExportResponse>().await;
-            debug!("export msig response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    let method = RpcFields::Export;
+    rpc::client()
+        .call(&method.value(), rpc::NoParams {})
+        .await
+        .map(|result| reqres::XmrRpcExportResponse { result })
 }
 
 /// Performs the xmr rpc 'import_multisig_info' method
-pub async fn import_multisig_info(info: Vec<String>) -> reqres::XmrRpcImportResponse {
+pub async fn import_multisig_info(info: Vec<String>) -> Result<reqres::XmrRpcImportResponse, rpc::RpcError> {
     info!("import msig wallet");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
+    let method = RpcFields::Import;
     let params = reqres::XmrRpcImportParams {
         info,
     };
-    let req = reqres::XmrRpcImportRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::Import.value(),
-        params,
-    };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcImportResponse>().await;
-            debug!("import msig info response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcImportResponse { result })
 }
 
 /// Performs the xmr rpc 'sign_multisig' method
-pub async fn sign_multisig(tx_data_hex: String) -> reqres::XmrRpcSignMultisigResponse {
+pub async fn sign_multisig(tx_data_hex: String) -> Result<reqres::XmrRpcSignMultisigResponse, rpc::RpcError> {
     info!("sign msig txset");
-    let client = reqwest::Client::new();
-    let host = get_rpc_host();
+    let method = RpcFields::SignMultisig;
     let params = reqres::XmrRpcSignMultisigParams {
         tx_data_hex,
     };
-    let req = reqres::XmrRpcSignMultisigRequest {
-        jsonrpc: RpcFields::JsonRpcVersion.value(),
-        id: RpcFields::Id.value(),
-        method: RpcFields::SignMultisig.value(),
-        params,
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcSignMultisigResponse { result })
+}
+
+/// Performs the xmr rpc 'submit_multisig' method
+pub async fn submit_multisig(
+    tx_data_hex: String,
+) -> Result<reqres::XmrRpcSubmitMultisigResponse, rpc::RpcError> {
+    info!("submit msig txset");
+    let method = RpcFields::SubmitMultisig;
+    let params = reqres::XmrRpcSubmitMultisigParams {
+        tx_data_hex,
+    };
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcSubmitMultisigResponse { result })
+}
+
+/// Performs the xmr rpc 'transfer' method. In a multisig wallet the
+/// response has a `multisig_txset` that the other signers must sign.
+pub async fn transfer(
+    destinations: Vec<reqres::Destination>,
+    subtract_fee_from_outputs: Vec<u32>,
+) -> Result<reqres::XmrRpcTransferResponse, rpc::RpcError> {
+    info!("transfer to {} destinations", destinations.len());
+    let method = RpcFields::Transfer;
+    let params = reqres::XmrRpcTransferParams {
+        subtract_fee_from_outputs,
+        destinations,
+        account_index: 0,
     };
-    let login: RpcLogin = get_rpc_creds();
-    match client.post(host).json(&req)
-    .send_with_digest_auth(&login.username, &login.credential).await {
-        Ok(response) => {
-            let res = response.json::<reqres::XmrRpcSignMultisigResponse>().await;
-            debug!("sign msig txset response: {:?}", res);
-            match res {
-                Ok(res) => res,
-                _ => Default::default(),
-            }
-        }
-        Err(_) => Default::default()
-    }
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcTransferResponse { result })
+}
+
+/// Performs the xmr rpc 'describe_transfer' method on a multisig txset
+pub async fn describe_transfer(
+    multisig_txset: String,
+) -> Result<reqres::XmrRpcDescribeTransferResponse, rpc::RpcError> {
+    info!("describe msig txset");
+    let method = RpcFields::DescribeTransfer;
+    let params = reqres::XmrRpcDescribeTransferParams {
+        multisig_txset,
+    };
+    rpc::client()
+        .call(&method.value(), params)
+        .await
+        .map(|result| reqres::XmrRpcDescribeTransferResponse { result })
 }
 // END Multisig
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_transfer_by_txid_result_test() {
+        let body = br#"{"id":"0","jsonrpc":"2.0","result":{"transfer":{"address":"5abc","amount":1000,
+            "confirmations":3,"fee":20,"height":100,"subaddr_index":{"major":0,"minor":2},
+            "timestamp":1700000000,"txid":"ff","type":"in"}}}"#;
+        let res: reqres::XmrRpcGetTransferByTxidResult = rpc::parse_response(body).unwrap();
+        assert_eq!(res.transfer.kind, "in");
+        assert_eq!(res.transfer.subaddr_index, reqres::SubaddressIndex { major: 0, minor: 2 });
+        assert!(res.transfers.is_empty());
+        // wallets without matching outputs omit the list
+        let empty = br#"{"id":"0","jsonrpc":"2.0","result":{}}"#;
+        let res: reqres::XmrRpcIncomingTransfersResult = rpc::parse_response(empty).unwrap();
+        assert!(res.transfers.is_empty());
+    }
+}
@@ -1,445 +1,1596 @@
-// use crate::customer;
-// use crate::models::*;
-// use crate::monero;
-// use crate::product;
-// use crate::reqres;
-// use crate::schema;
-// use crate::utils;
-// use crate::vendor;
-// use diesel::prelude::*;
-// use log::{debug, error, info};
-
-// enum StatusType {
-//     Delivered,
-//     MultisigMissing,
-//     MulitsigComplete,
-//     Shipped,
-// }
-
-// impl StatusType {
-//     pub fn value(&self) -> String {
-//         match *self {
-//             StatusType::Delivered => String::from("Delivered"),
-//             StatusType::MultisigMissing => String::from("MultisigMissing"),
-//             StatusType::MulitsigComplete => String::from("MulitsigComplete"),
-//             StatusType::Shipped => String::from("Shipped"),
-//         }
-//     }
-// }
-
-// enum UpdateType {
-//     CustomerKex1,
-//     CustomerKex2,
-//     CustomerKex3,
-//     CustomerMultisigInfo,
-//     Deliver,
-//     Hash,
-//     Ship,
-//     Subaddress,
-//     VendorKex1,
-//     VendorKex2,
-//     VendorKex3,
-//     VendorMultisigInfo,
-//     Quantity,
-// }
-
-// impl UpdateType {
-//     pub fn value(&self) -> i32 {
-//         match *self {
-//             UpdateType::CustomerKex1 => 0,         // make output from customer
-//             UpdateType::CustomerKex2 => 1,         // use this for funding kex
-//             UpdateType::CustomerKex3 => 2,         // might need this later?
-//             UpdateType::CustomerMultisigInfo => 3, // prepare output from customer
-//             UpdateType::Deliver => 4,              // customer has received the item, released txset
-//             UpdateType::Hash => 5,                 // tx hash from funding the wallet order
-//             UpdateType::Ship => 6,                 // update ship date, app doesn't store tracking numbers
-//             UpdateType::Subaddress => 7,           // update address for payout
-//             UpdateType::VendorKex1 => 8,           // make output from vendor
-//             UpdateType::VendorKex2 => 9,           // use this for funding kex
-//             UpdateType::VendorKex3 => 10,          // might need this later?
-//             UpdateType::VendorMultisigInfo => 11,  // prepare output from vendor
-//             UpdateType::Quantity => 12,            // this can be updated until wallet is funded
-//         }
-//     }
-// }
-
-// /// Create a skeleton for order
-// pub async fn create(cid: String, pid: String) -> Order {
-//     use crate::schema::orders;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let ts = chrono::offset::Utc::now().timestamp();
-//     let oid: String = format!("O{}", utils::generate_rnd());
-//     let m_product: Product = product::find(String::from(&pid)).await;
-//     let new_order = NewOrder {
-//         orid: &oid,
-//         c_id: &cid,
-//         p_id: &pid,
-//         v_id: &m_product.v_id,
-//         o_cust_kex_1: "",
-//         o_cust_kex_2: "",
-//         o_cust_kex_3: "",
-//         o_xmr_address: "",
-//         o_cust_msig_info: "",
-//         o_cust_msig_txset: "",
-//         o_date: &ts,
-//         o_deliver_date: &0,
-//         o_ship_date: &0,
-//         o_hash: "",
-//         o_msig_prepare: "",
-//         o_msig_make: "",
-//         o_msig_kex_1: "",
-//         o_msig_kex_2: "",
-//         o_msig_kex_3: "",
-//         o_subaddress: "",
-//         o_status: &StatusType::MultisigMissing.value(),
-//         o_quantity: &0,
-//         o_vend_kex_1: "",
-//         o_vend_kex_2: "",
-//         o_vend_kex_3: "",
-//         o_vend_msig_info: "",
-//         o_vend_msig_txset: "",
-//     };
-//     debug!("insert order: {:?}", new_order);
-//     let m_wallet = monero::create_wallet(String::from(&oid)).await;
-//     if !m_wallet {
-//         error!("error creating wallet");
-//     }
-//     diesel::insert_into(orders::table)
-//         .values(&new_order)
-//         .get_result(connection)
-//         .expect("error saving new order")
-//     // create wallet for the order
-// }
-
-// /// Modify order lifecycle
-// pub async fn modify(_id: String, pid: String, data: String, update_type: i32) -> Order {
-//     use self::schema::orders::dsl::*;
-//     let t_id: String = String::from(&_id);
-//     let is_customer = is_customer(t_id);
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let old_search = orders
-//         .filter(schema::orders::orid.eq(String::from(&_id)))
-//         .first::<Order>(connection);
-//     let old = match old_search {
-//         Ok(r) => r,
-//         _ => {
-//             error!("error finding old order");
-//             Default::default()
-//         }
-//     };
-//     // this else if chain is awful, TODO: refactor
-//     if update_type == UpdateType::CustomerKex1.value() && is_customer {
-//         info!("modify order customer kex 1");
-//         let m = diesel::update(orders.find(String::from(&_id)))
-//             .set(o_cust_kex_1.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => {
-//                 if old.o_vend_kex_1 != String::from("") {
-//                     finalize(connection, String::from(&_id), old).await;
-//                 }
-//                 m
-//             },
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::CustomerKex2.value() && is_customer {
-//         info!("modify customer kex 2");
-//         let m = diesel::update(orders.find(String::from(&_id)))
-//             .set(o_cust_kex_2.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => {
-//                 if old.o_vend_kex_2 != String::from("") {
-//                     update_multisig_info(connection, String::from(&_id), old).await;
-//                 }
-//                 m
-//             },
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::CustomerKex3.value() && is_customer {
-//         info!("modify customer kex 3");
-//         let m = diesel::update(orders.find(_id))
-//             .set(o_cust_kex_3.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::CustomerMultisigInfo.value() && is_customer {
-//         info!("modify customer multisig info");
-//         let m = diesel::update(orders.find(String::from(&_id)))
-//             .set(o_cust_msig_info.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => {
-//                 if old.o_vend_msig_info != String::from("") {
-//                     prepare_and_make(connection, String::from(&_id), old).await;
-//                 }
-//                 m
-//             }
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Deliver.value() && !is_customer {
-//         info!("modify devliver date");
-//         let deliver_date = chrono::offset::Utc::now().timestamp();
-//         let m = diesel::update(orders.find(_id))
-//             .set((o_deliver_date.eq(deliver_date), 
-//                 o_status.eq(StatusType::Delivered.value()),
-//                 o_cust_msig_txset.eq(data)
-//             ))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Hash.value() && is_customer {
-//         info!("modify order hash");
-//         let m = diesel::update(orders.find(_id))
-//             .set(o_hash.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Ship.value() && !is_customer {
-//         info!("modify order ship date");
-//         let ship_date = match data.parse::<i64>() {
-//             Ok(n) => n,
-//             Err(_e) => 0,
-//         };
-//         let m = diesel::update(orders.find(_id))
-//             .set((o_ship_date.eq(ship_date), o_status.eq(StatusType::Shipped.value())))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Subaddress.value() && !is_customer {
-//         info!("modify order subaddress");
-//         let m = diesel::update(orders.find(_id))
-//             .set(o_subaddress.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::VendorKex1.value() && !is_customer {
-//         info!("modify order customer kex 1");
-//         let m = diesel::update(orders.find(String::from(&_id)))
-//             .set(o_vend_kex_1.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => {
-//                 if old.o_cust_kex_1 != String::from("") {
-//                     finalize(connection, String::from(&_id), old).await;
-//                 }
-//                 m
-//             }
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::VendorKex2.value() && !is_customer {
-//         info!("modify vendor kex 2");
-//         let m = diesel::update(orders.find(String::from(&_id)))
-//             .set(o_vend_kex_2.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => {
-//                 if old.o_cust_kex_1 != String::from("") {
-//                     update_multisig_info(connection, String::from(&_id), old).await;
-//                 }
-//                 m
-//             },
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::VendorKex3.value() && !is_customer {
-//         info!("modify vendor kex 3");
-//         let m = diesel::update(orders.find(_id))
-//             .set(o_vend_kex_3.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::VendorMultisigInfo.value() && !is_customer {
-//         info!("modify vendor multisig info");
-//         let m = diesel::update(orders.find(String::from(&_id)))
-//             .set(o_vend_msig_info.eq(data))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => {
-//                 if old.o_cust_msig_info != String::from("") {
-//                     prepare_and_make(connection, String::from(&_id), old).await;
-//                 }
-//                 m
-//             },
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Quantity.value() && is_customer {
-//         info!("modify quantity");
-//         // check qty/in_stock
-//         let m_product: Product = product::find(pid).await;
-//         let qty = match data.parse::<i64>() {
-//             Ok(n) => n,
-//             Err(_e) => 0,
-//         };
-//         let is_invalid_qty: bool = !m_product.in_stock || m_product.qty == 0 || qty > m_product.qty;
-//         if is_invalid_qty {
-//             return Default::default();
-//         }
-//         let m = diesel::update(orders.find(_id))
-//             .set(o_quantity.eq(qty))
-//             .get_result::<Order>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     }
-//     Default::default()
-// }
-
-// /// Lookup order
-// pub async fn find(oid: String) -> Order {
-//     use self::schema::orders::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let result = orders
-//         .filter(schema::orders::orid.eq(oid))
-//         .first::<Order>(connection);
-//     match result {
-//         Ok(r) => r,
-//         _ => {
-//             error!("error finding order");
-//             Default::default()
-//         }
-//     }
-// }
-
-// /// Lookup all orders for customer or vendor
-// pub async fn find_all(address: String, corv: String) -> Vec<Order> {
-//     use self::schema::orders::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     if corv == utils::LoginType::Customer.value() {
-//         let m_cust: Customer = customer::find(address).await;
-//         let results = orders
-//             .filter(schema::orders::c_id.eq(m_cust.cid))
-//             .load::<Order>(connection);
-//         return match results {
-//             Ok(r) => {
-//                 info!("found customer orders");
-//                 r
-//             }
-//             _ => {
-//                 error!("error finding customer orders");
-//                 let v: Vec<Order> = Vec::new();
-//                 v
-//             }
-//         };
-//     } else {
-//         let m_vend: Vendor = vendor::find(address).await;
-//         let results = orders
-//             .filter(schema::orders::v_id.eq(m_vend.vid))
-//             .load::<Order>(connection);
-//         return match results {
-//             Ok(r) => {
-//                 info!("found vendor orders");
-//                 r
-//             }
-//             _ => {
-//                 error!("error finding vendor orders");
-//                 let v: Vec<Order> = Vec::new();
-//                 v
-//             }
-//         };
-//     };
-// }
-
-// pub fn is_customer(id: String) -> bool {
-//     let first: char = id.chars().nth(0).unwrap();
-//     debug!("id starts with: {}", first);
-//     return first == 'C';
-// }
-
-// /// Attempt to update prepare and make multisig info for the app
-// async fn prepare_and_make(connection: &mut PgConnection, _id: String, old: Order) {
-//     let mut m_wallet = monero::open_wallet(String::from(&old.orid)).await;
-//     if !m_wallet {
-//         error!("error opening wallet {}", &old.orid);
-//     }
-//     use self::schema::orders::dsl::*;
-//     let mut info: Vec<String> = Vec::new();
-//     info.push(old.o_vend_msig_info);
-//     info.push(old.o_cust_msig_info);
-//     let app_prepare: reqres::XmrRpcPrepareResponse = monero::prepare_wallet().await;
-//     let prepare_update = diesel::update(orders.find(String::from(&_id)))
-//         .set(o_msig_prepare.eq(String::from(&app_prepare.result.multisig_info)))
-//         .get_result::<Order>(connection);
-//     match prepare_update {
-//         Ok(_) => info!("prepare info update"),
-//         Err(_) => error!("error updating prepare info"),
-//     };
-//     let make: reqres::XmrRpcMakeResponse = monero::make_wallet(info).await;
-//     let make_update = diesel::update(orders.find(_id))
-//         .set(o_msig_kex_1.eq(make.result.multisig_info))
-//         .get_result::<Order>(connection);
-//     match make_update {
-//         Ok(_) => info!("make info update"),
-//         Err(_) => error!("error updating make info"),
-//     };
-//     m_wallet = monero::close_wallet(String::from(&old.orid)).await;
-//     if !m_wallet {
-//         error!("error closing wallet {}", &old.orid);
-//     }
-// }
-
-// /// Attempts to finalize the wallet
-// async fn finalize(connection: &mut PgConnection, _id: String, old: Order) {
-//     let mut m_wallet = monero::open_wallet(String::from(&old.orid)).await;
-//     if !m_wallet {
-//         error!("error opening wallet {}", &old.orid);
-//     }
-//     use self::schema::orders::dsl::*;
-//     let mut info: Vec<String> = Vec::new();
-//     info.push(old.o_vend_kex_1);
-//     info.push(old.o_cust_kex_1);
-//     let app_finalize: reqres::XmrRpcFinalizeResponse = monero::finalize_wallet(info).await;
-//     let finalize_update = diesel::update(orders.find(String::from(&_id)))
-//         .set((o_xmr_address.eq(String::from(&app_finalize.result.address)),
-//         o_status.eq(StatusType::MulitsigComplete.value())
-//         ))
-//         .get_result::<Order>(connection);
-//     match finalize_update {
-//         Ok(_) => info!("finalize info update"),
-//         Err(_) => error!("error finalizing info"),
-//     };
-//     m_wallet = monero::close_wallet(String::from(&old.orid)).await;
-//     if !m_wallet {
-//         error!("error closing wallet {}", &old.orid);
-//     }
-// }
-
-// /// Used to update key images after funding the multisig wallet
-// async fn update_multisig_info(connection: &mut PgConnection, _id: String, old: Order) {
-//     let mut m_wallet = monero::open_wallet(String::from(&old.orid)).await;
-//     if !m_wallet {
-//         error!("error opening wallet {}", &old.orid);
-//     }
-//     use self::schema::orders::dsl::*;
-//     let mut info: Vec<String> = Vec::new();
-//     info.push(old.o_vend_kex_2);
-//     info.push(old.o_cust_kex_2);
-//     let import: reqres::XmrRpcImportResponse = monero::import_multisig_info(info).await;
-//     if import.result.n_outputs == 0 {
-//         error!("error importing multisig info");
-//     }
-//     let export: reqres::XmrRpcExportResponse = monero::export_multisig_info().await;
-//     let info_update = diesel::update(orders.find(String::from(&_id)))
-//         .set(o_msig_kex_2.eq(String::from(&export.result.info)))
-//         .get_result::<Order>(connection);
-//     match info_update {
-//         Ok(_) => info!("msig info update"),
-//         Err(_) => error!("error updating msig export info"),
-//     };
-//     m_wallet = monero::close_wallet(String::from(&old.orid)).await;
-//     if !m_wallet {
-//         error!("error closing wallet {}", &old.orid);
-//     }
-// }
+// Order repo/service layer
+use crate::{
+    args, auth, commission, customer, db, dispute, models::*, monero, product, rates, reqres, rpc, shipping,
+    utils, vendor, wallet,
+};
+use clap::Parser;
+use log::{debug, error, info, warn};
+
+/// Signatures needed to spend from an order wallet
+pub const THRESHOLD: u32 = 2;
+/// Keys in an order wallet: customer, vendor and the market. The market key
+/// lives in the app's order wallet and is used by the mediator.
+pub const PARTICIPANTS: u32 = 3;
+
+/// Order lifecycle, see `can_transition` for the allowed moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    Created,
+    MultisigPreparing,
+    MultisigReady,
+    Funded,
+    Shipped,
+    Delivered,
+    Disputed,
+    Released,
+    Refunded,
+    /// a mediator split the funds between the vendor and customer
+    Settled,
+    Cancelled,
+    Expired,
+}
+
+impl OrderStatus {
+    pub fn value(&self) -> String {
+        match *self {
+            OrderStatus::Created => String::from("Created"),
+            OrderStatus::MultisigPreparing => String::from("MultisigPreparing"),
+            OrderStatus::MultisigReady => String::from("MultisigReady"),
+            OrderStatus::Funded => String::from("Funded"),
+            OrderStatus::Shipped => String::from("Shipped"),
+            OrderStatus::Delivered => String::from("Delivered"),
+            OrderStatus::Disputed => String::from("Disputed"),
+            OrderStatus::Released => String::from("Released"),
+            OrderStatus::Refunded => String::from("Refunded"),
+            OrderStatus::Settled => String::from("Settled"),
+            OrderStatus::Cancelled => String::from("Cancelled"),
+            OrderStatus::Expired => String::from("Expired"),
+        }
+    }
+    pub fn from_value(v: &str) -> Option<OrderStatus> {
+        match v {
+            "Created" => Some(OrderStatus::Created),
+            "MultisigPreparing" => Some(OrderStatus::MultisigPreparing),
+            "MultisigReady" => Some(OrderStatus::MultisigReady),
+            "Funded" => Some(OrderStatus::Funded),
+            "Shipped" => Some(OrderStatus::Shipped),
+            "Delivered" => Some(OrderStatus::Delivered),
+            "Disputed" => Some(OrderStatus::Disputed),
+            "Released" => Some(OrderStatus::Released),
+            "Refunded" => Some(OrderStatus::Refunded),
+            "Settled" => Some(OrderStatus::Settled),
+            "Cancelled" => Some(OrderStatus::Cancelled),
+            "Expired" => Some(OrderStatus::Expired),
+            _ => None,
+        }
+    }
+    /// Stock is still reserved (not committed) before funding
+    pub fn is_unfunded(&self) -> bool {
+        matches!(
+            *self,
+            OrderStatus::Created | OrderStatus::MultisigPreparing | OrderStatus::MultisigReady
+        )
+    }
+    /// No further transitions are possible, apart from refunding what an
+    /// expired order received
+    pub fn is_final(&self) -> bool {
+        matches!(
+            *self,
+            OrderStatus::Released
+                | OrderStatus::Refunded
+                | OrderStatus::Settled
+                | OrderStatus::Cancelled
+                | OrderStatus::Expired
+        )
+    }
+}
+
+/// Who is acting on an order. The system role is used by background jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Customer,
+    Vendor,
+    Mediator,
+    System,
+}
+
+impl Role {
+    pub fn value(&self) -> String {
+        match *self {
+            Role::Customer => String::from("customer"),
+            Role::Vendor => String::from("vendor"),
+            Role::Mediator => String::from("mediator"),
+            Role::System => String::from("system"),
+        }
+    }
+}
+
+/// Allowed status moves and the roles that may make them
+pub fn can_transition(from: OrderStatus, to: OrderStatus, role: Role) -> bool {
+    use OrderStatus::*;
+    use Role::*;
+    match (from, to) {
+        (Created, MultisigPreparing) => matches!(role, Customer | Vendor | System),
+        (MultisigPreparing, MultisigReady) => matches!(role, Customer | Vendor | System),
+        // funding is confirmed on chain, not by either party
+        (MultisigReady, Funded) => role == System,
+        (Created | MultisigPreparing | MultisigReady, Cancelled) => matches!(role, Customer | Vendor | System),
+        (Created | MultisigPreparing | MultisigReady, Expired) => role == System,
+        // the partial payment of an underpaid order goes back to the customer
+        (Expired, Refunded) => role == System,
+        (Funded, Shipped) => role == Vendor,
+        (Funded, Refunded) => matches!(role, Vendor | Mediator | System),
+        (Shipped, Delivered) => matches!(role, Customer | System),
+        (Shipped, Disputed) => matches!(role, Customer | Vendor),
+        (Delivered, Released) => matches!(role, Customer | Vendor | System),
+        // the mediator decides, the system records a payout the parties settled on
+        (Disputed, Released | Refunded | Settled) => matches!(role, Mediator | System),
+        _ => false,
+    }
+}
+
+/// Move the order to a new status, None if the move isn't allowed for the role
+pub fn transition(o: Order, to: OrderStatus, role: Role, now: i64) -> Option<Order> {
+    let from = OrderStatus::from_value(&o.o_status)?;
+    if !can_transition(from, to, role) {
+        return None;
+    }
+    let mut o_history = o.o_history.clone();
+    o_history.push(StatusChange { status: to.value(), role: role.value(), timestamp: now });
+    Some(Order {
+        o_status: to.value(),
+        o_ship_date: if to == OrderStatus::Shipped { now } else { o.o_ship_date },
+        o_deliver_date: if to == OrderStatus::Delivered { now } else { o.o_deliver_date },
+        o_history,
+        ..o
+    })
+}
+
+/// Order changes submitted by the customer or vendor. Multisig fields are
+/// stored in the column for the submitter's role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateType {
+    MultisigInfo,   // prepare output
+    Kex,            // next key exchange round message, make output first
+    MultisigExport, // multisig info export after funding
+    Deliver,        // customer has received the item, optional release txset
+    Hash,           // tx hash from funding the order wallet
+    Ship,           // vendor shipped, app doesn't store tracking numbers
+    Subaddress,     // vendor payout address
+    Quantity,       // this can be updated until the multisig wallet is prepared
+    Release,        // propose a txset paying the vendor subaddress
+    Refund,         // propose a txset paying the customer refund address
+    Sign,           // co-sign the proposed txset, the app submits it
+    RefundAddress,  // customer refund address
+    Cancel,         // cancel before funding, or agree to cancel a funded order
+}
+
+impl UpdateType {
+    pub fn value(&self) -> i32 {
+        match *self {
+            UpdateType::MultisigInfo => 0,
+            UpdateType::Kex => 1,
+            UpdateType::MultisigExport => 2,
+            UpdateType::Deliver => 3,
+            UpdateType::Hash => 4,
+            UpdateType::Ship => 5,
+            UpdateType::Subaddress => 6,
+            UpdateType::Quantity => 7,
+            UpdateType::Release => 8,
+            UpdateType::Refund => 9,
+            UpdateType::Sign => 10,
+            UpdateType::RefundAddress => 11,
+            UpdateType::Cancel => 12,
+        }
+    }
+    pub fn from_value(v: i32) -> Option<UpdateType> {
+        match v {
+            0 => Some(UpdateType::MultisigInfo),
+            1 => Some(UpdateType::Kex),
+            2 => Some(UpdateType::MultisigExport),
+            3 => Some(UpdateType::Deliver),
+            4 => Some(UpdateType::Hash),
+            5 => Some(UpdateType::Ship),
+            6 => Some(UpdateType::Subaddress),
+            7 => Some(UpdateType::Quantity),
+            8 => Some(UpdateType::Release),
+            9 => Some(UpdateType::Refund),
+            10 => Some(UpdateType::Sign),
+            11 => Some(UpdateType::RefundAddress),
+            12 => Some(UpdateType::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// Where a payout txset sends the order wallet funds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutType {
+    Release,
+    Refund,
+    /// shared between vendor and customer on the dispute's terms
+    Split,
+}
+
+impl PayoutType {
+    pub fn value(&self) -> String {
+        match *self {
+            PayoutType::Release => String::from("release"),
+            PayoutType::Refund => String::from("refund"),
+            PayoutType::Split => String::from("split"),
+        }
+    }
+    pub fn from_value(v: &str) -> Option<PayoutType> {
+        match v {
+            "release" => Some(PayoutType::Release),
+            "refund" => Some(PayoutType::Refund),
+            "split" => Some(PayoutType::Split),
+            _ => None,
+        }
+    }
+    /// Order status once the payout is submitted
+    pub fn status(&self) -> OrderStatus {
+        match *self {
+            PayoutType::Release => OrderStatus::Released,
+            PayoutType::Refund => OrderStatus::Refunded,
+            PayoutType::Split => OrderStatus::Settled,
+        }
+    }
+}
+
+/// What the funding watcher saw in an order wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundingStatus {
+    Unpaid,
+    /// the full amount arrived but isn't confirmed yet
+    Pending,
+    Underpaid,
+    Paid,
+    Overpaid,
+}
+
+impl FundingStatus {
+    pub fn value(&self) -> String {
+        match *self {
+            FundingStatus::Unpaid => String::from("unpaid"),
+            FundingStatus::Pending => String::from("pending"),
+            FundingStatus::Underpaid => String::from("underpaid"),
+            FundingStatus::Paid => String::from("paid"),
+            FundingStatus::Overpaid => String::from("overpaid"),
+        }
+    }
+}
+
+/// Key for the list of orids belonging to a customer or vendor
+fn get_list_key(id: &String) -> String {
+    format!("ol{}", id)
+}
+
+/// Key for the list of orids that aren't final, scanned by background jobs
+fn get_open_key() -> String {
+    String::from("olopen")
+}
+
+/// Get the funding confirmations command line configuration
+fn get_confirmations() -> u64 {
+    let args = args::Args::parse();
+    args.confirmations
+}
+
+/// Order deadlines in seconds
+#[derive(Debug, Clone, Copy)]
+pub struct Deadlines {
+    /// unfunded orders expire this long after creation
+    pub expire: i64,
+    /// funded orders must ship within this, after that the customer can
+    /// be refunded with the market key
+    pub ship: i64,
+    /// shipped orders finalize to the vendor after this unless disputed
+    pub finalize: i64,
+}
+
+/// Get the order deadline command line configuration
+pub fn get_deadlines() -> Deadlines {
+    let args = args::Args::parse();
+    Deadlines {
+        expire: args.expire_hours * 60 * 60,
+        ship: args.ship_days * 24 * 60 * 60,
+        finalize: args.finalize_days * 24 * 60 * 60,
+    }
+}
+
+/// What the deadline job does with an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineAction {
+    /// unfunded past the expiry, the stock is released and anything
+    /// received is refunded
+    Expire,
+    /// not shipped in time, the market co-signs the customer's refund
+    Refund,
+    /// the dispute window passed, the order is delivered and released
+    Finalize,
+    /// finalized, or marked delivered by a customer who never proposed a
+    /// release, and the market hasn't proposed the release yet
+    ProposeRelease,
+    /// expired with a partial payment the market hasn't proposed to refund
+    ProposeRefund,
+}
+
+/// Amount in piconero the order wallet must be funded with: the unit price
+/// of the chosen variant (or base price) times quantity plus the shipping
+/// price. Fiat priced products convert at `rate` cents per xmr. None when
+/// the amount overflows, e.g. for a huge quantity.
+pub fn get_amount(p: &Product, vrid: &String, s: &Shipping, quantity: i64, rate: i64) -> Option<i64> {
+    let subtotal = product::get_price(p, vrid).checked_mul(quantity)?;
+    let amount = if p.currency.is_empty() { subtotal } else { rates::to_piconero(subtotal, rate)? };
+    amount.checked_add(s.price)
+}
+
+/// Lookup order
+pub fn find(oid: &String) -> Order {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &String::from(oid));
+    if r == utils::empty_string() {
+        error!("error finding order");
+        return Default::default()
+    }
+    Order::from_db(String::from(oid), r)
+}
+
+/// Lookup all orders for customer or vendor
+pub fn find_all(id: &String) -> Vec<Order> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key(id));
+    if r == utils::empty_string() {
+        info!("no orders found for {}", id);
+        return Vec::new()
+    }
+    let orders: Vec<Order> = r.split(',')
+        .map(|oid| find(&String::from(oid)))
+        .filter(|o| o.orid != utils::empty_string())
+        .collect();
+    debug!("found {} orders for {}", orders.len(), id);
+    orders
+}
+
+/// Role of the address on the order, None if it isn't a party to it
+pub fn get_role(o: &Order, address: &String) -> Option<Role> {
+    if &customer::find(&o.c_id).xmr_address == address {
+        return Some(Role::Customer);
+    }
+    if &vendor::find(&o.v_id).v_xmr_address == address {
+        return Some(Role::Vendor);
+    }
+    if auth::is_admin(address) || dispute::is_mediator_for(&o.orid, address) {
+        return Some(Role::Mediator);
+    }
+    None
+}
+
+/// Stock change that goes with a status change. Reserved stock is
+/// released or committed, a refund before shipping restocks.
+pub fn get_stock_change(from: Option<OrderStatus>, to: OrderStatus) -> Option<product::StockChange> {
+    let is_unfunded = from.is_some_and(|f| f.is_unfunded());
+    match to {
+        OrderStatus::Funded => Some(product::StockChange::Commit),
+        OrderStatus::Cancelled | OrderStatus::Expired if is_unfunded => Some(product::StockChange::Release),
+        OrderStatus::Refunded if from == Some(OrderStatus::Funded) => Some(product::StockChange::Restock),
+        _ => None,
+    }
+}
+
+/// Apply a status transition to the current order record and persist it.
+/// The stock change is made in the same transaction as the order write.
+pub fn set_status(oid: &String, to: OrderStatus, role: Role) -> Order {
+    let now = chrono::offset::Utc::now().timestamp();
+    let u_order = update_with_stock(oid, |o| {
+        let from = OrderStatus::from_value(&o.o_status);
+        match transition(o, to, role, now) {
+            Some(u) => {
+                let changes = get_stock_change(from, to).map(|c| (u.o_quantity, c)).into_iter().collect();
+                Some((u, changes))
+            }
+            None => {
+                error!("{} can't move order {} from {:?} to {:?}", role.value(), oid, from, to);
+                None
+            }
+        }
+    });
+    if u_order.orid == utils::empty_string() {
+        return u_order;
+    }
+    info!("order {} is now {}", oid, u_order.o_status);
+    // expired orders stay open until what they received is refunded
+    if to.is_final() && !is_refund_due(&u_order) {
+        let s = db::Interface::open();
+        db::Interface::remove_from_list(&s.env, &s.handle, &get_open_key(), oid);
+    }
+    u_order
+}
+
+/// Read, change and write the order in one transaction so the customer and
+/// vendor submitting at the same time don't overwrite each other
+fn update<F>(oid: &String, f: F) -> Order
+where
+    F: FnOnce(Order) -> Option<Order>,
+{
+    update_with_stock(oid, |o| f(o).map(|u| (u, Vec::new())))
+}
+
+/// Same as `update`, `f` also returns the stock changes, in units, to make
+/// on the order's product in the same transaction
+fn update_with_stock<F>(oid: &String, f: F) -> Order
+where
+    F: FnOnce(Order) -> Option<(Order, Vec<(i64, product::StockChange)>)>,
+{
+    let s = db::Interface::open();
+    let mut u_order: Order = Default::default();
+    db::Interface::transaction(&s.env, &s.handle, |db| {
+        let r = String::from(db.get::<&str>(oid).unwrap_or(""));
+        if r == utils::empty_string() {
+            error!("error finding order");
+            return false;
+        }
+        let (o, changes) = match f(Order::from_db(String::from(oid), r)) {
+            Some(u) => u,
+            None => return false,
+        };
+        if !changes.is_empty() {
+            let r = String::from(db.get::<&str>(&o.p_id).unwrap_or(""));
+            let mut u_product = (r != utils::empty_string()).then(|| Product::from_db(String::from(&o.p_id), r));
+            for &(quantity, c) in &changes {
+                info!("{:?} {} units of product {} for order {}", c, quantity, o.p_id, oid);
+                u_product = u_product.and_then(|p| product::apply_stock(p, &o.o_variant, quantity, c));
+            }
+            match u_product {
+                Some(p) => {
+                    if db.set(&p.pid.as_str(), &Product::to_db(&p).as_str()).is_err() {
+                        error!("failed to write product {}", &p.pid);
+                        return false;
+                    }
+                }
+                // the refund is already on chain, record it even if the product is gone
+                None if changes.iter().all(|(_, c)| *c == product::StockChange::Restock) => {
+                    error!("failed to restock product {} for order {}", &o.p_id, oid);
+                }
+                None => {
+                    error!("insufficient stock of product {} for order {}", &o.p_id, oid);
+                    return false;
+                }
+            }
+        }
+        if db.set(&o.orid.as_str(), &Order::to_db(&o).as_str()).is_err() {
+            error!("failed to write order {}", oid);
+            return false;
+        }
+        u_order = o;
+        true
+    });
+    u_order
+}
+
+/// Create an order and its multisig wallet, reserving the stock
+pub async fn create(cid: &String, address: &String, pid: &String, r: &reqres::OrderRequest) -> Order {
+    let m_customer: Customer = customer::find(cid);
+    if &m_customer.xmr_address != address {
+        error!("customer {} can't create orders for {}", cid, address);
+        return Default::default();
+    }
+    let m_product: Product = product::find(pid);
+    let vrid: String = r.variant.clone().unwrap_or_default();
+    let has_variant = vrid.is_empty() == m_product.variants.is_empty()
+        && (vrid.is_empty() || product::get_variant(&m_product, &vrid).vrid == vrid);
+    if m_product.pid == utils::empty_string() || !has_variant || r.quantity <= 0 {
+        error!("invalid order for product {}", pid);
+        return Default::default();
+    }
+    let m_shipping: Shipping = shipping::find(&r.shipping);
+    if m_shipping.v_id != m_product.v_id || !shipping::ships_to(&m_shipping, &r.region) {
+        error!("shipping option {} is not available for {}", &r.shipping, &r.region);
+        return Default::default();
+    }
+    let rate: i64 = if m_product.currency.is_empty() {
+        0
+    } else {
+        rates::lock_rate(&m_product.currency).await
+    };
+    if !m_product.currency.is_empty() && rate == 0 {
+        error!("no {} rate available", &m_product.currency);
+        return Default::default();
+    }
+    let o_amount = match get_amount(&m_product, &vrid, &m_shipping, r.quantity, rate) {
+        Some(a) => a,
+        None => {
+            error!("order amount for {} units of product {} is too large", r.quantity, pid);
+            return Default::default();
+        }
+    };
+    let o_fee = commission::get_fee(&commission::find(&m_product.category), o_amount);
+    let ts = chrono::offset::Utc::now().timestamp();
+    let orid: String = format!("ord{}", utils::generate_rnd());
+    // create wallet for the order
+    if let Err(e) = wallet::create(&orid).await {
+        error!("error creating wallet {}: {}", &orid, e);
+        return Default::default();
+    }
+    let o_fee_address = if o_fee > 0 { commission::create_address(&orid).await } else { utils::empty_string() };
+    if o_fee > 0 && o_fee_address == utils::empty_string() {
+        error!("error creating commission address for order {}", &orid);
+        return Default::default();
+    }
+    let new_order = Order {
+        orid: String::from(&orid),
+        c_id: String::from(cid),
+        p_id: String::from(pid),
+        v_id: String::from(&m_product.v_id),
+        o_date: ts,
+        o_status: OrderStatus::Created.value(),
+        o_quantity: r.quantity,
+        o_variant: vrid,
+        o_shipping: String::from(&m_shipping.sid),
+        o_amount,
+        o_fee,
+        o_fee_address,
+        o_currency: String::from(&m_product.currency),
+        o_rate: rate,
+        o_history: vec![StatusChange {
+            status: OrderStatus::Created.value(),
+            role: Role::Customer.value(),
+            timestamp: ts,
+        }],
+        ..Default::default()
+    };
+    debug!("insert order: {:?}", &new_order);
+    if !product::update_stock(&new_order, product::StockChange::Reserve) {
+        return Default::default();
+    }
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_list_key(cid), &orid);
+    db::Interface::append(&s.env, &s.handle, &get_list_key(&new_order.v_id), &orid);
+    db::Interface::append(&s.env, &s.handle, &get_open_key(), &orid);
+    new_order
+}
+
+/// Modify order lifecycle, multisig steps run once both parties submit
+pub async fn modify(oid: &String, address: &String, update_type: UpdateType, data: String) -> Order {
+    let f_order: Order = find(oid);
+    let role = match get_role(&f_order, address) {
+        Some(r @ (Role::Customer | Role::Vendor)) => r,
+        // the mediator co-signs refunds with the market key
+        Some(Role::Mediator) if update_type == UpdateType::Sign => Role::Mediator,
+        _ => {
+            error!("{} can't modify order {}", address, oid);
+            return Default::default();
+        }
+    };
+    let is_customer = role == Role::Customer;
+    let status = OrderStatus::from_value(&f_order.o_status);
+    info!("modify order {} {:?}", oid, update_type);
+    match update_type {
+        UpdateType::MultisigInfo => {
+            let preparing = [Some(OrderStatus::Created), Some(OrderStatus::MultisigPreparing)];
+            if !preparing.contains(&status) {
+                return Default::default();
+            }
+            if status == Some(OrderStatus::Created) {
+                set_status(oid, OrderStatus::MultisigPreparing, role);
+            }
+            let u_order = update(oid, |o| {
+                if is_customer {
+                    Some(Order { o_cust_msig_info: data, ..o })
+                } else {
+                    Some(Order { o_vend_msig_info: data, ..o })
+                }
+            });
+            let ready = u_order.o_cust_msig_info != utils::empty_string()
+                && u_order.o_vend_msig_info != utils::empty_string()
+                && u_order.o_msig_prepare == utils::empty_string();
+            if ready { prepare_and_make(u_order).await } else { u_order }
+        }
+        UpdateType::Kex => {
+            if status != Some(OrderStatus::MultisigPreparing) {
+                return Default::default();
+            }
+            let u_order = update(oid, |o| {
+                let mut kex = if is_customer { o.o_cust_kex.clone() } else { o.o_vend_kex.clone() };
+                if !can_submit_kex(&kex, &o.o_msig_kex) {
+                    error!("order {} isn't ready for kex round {}", &o.orid, kex.len());
+                    return None;
+                }
+                kex.push(data);
+                if is_customer {
+                    Some(Order { o_cust_kex: kex, ..o })
+                } else {
+                    Some(Order { o_vend_kex: kex, ..o })
+                }
+            });
+            match next_round(&u_order) {
+                Some(round) => exchange_keys(u_order, round).await,
+                None => u_order,
+            }
+        }
+        UpdateType::MultisigExport => {
+            let u_order = update(oid, |o| {
+                if is_customer {
+                    Some(Order { o_cust_msig_export: data, ..o })
+                } else {
+                    Some(Order { o_vend_msig_export: data, ..o })
+                }
+            });
+            let ready = u_order.o_cust_msig_export != utils::empty_string()
+                && u_order.o_vend_msig_export != utils::empty_string();
+            if ready { update_multisig_info(u_order).await } else { u_order }
+        }
+        UpdateType::Deliver if is_customer => {
+            let u_order = set_status(oid, OrderStatus::Delivered, role);
+            if data == utils::empty_string() || u_order.orid == utils::empty_string() {
+                return u_order;
+            }
+            propose(u_order, role, PayoutType::Release, data).await
+        }
+        UpdateType::Hash if is_customer => update(oid, |o| Some(Order { o_hash: data, ..o })),
+        UpdateType::Ship if !is_customer => set_status(oid, OrderStatus::Shipped, role),
+        UpdateType::Subaddress if !is_customer => update(oid, |o| Some(Order { o_subaddress: data, ..o })),
+        UpdateType::Quantity if is_customer && status == Some(OrderStatus::Created) => {
+            let qty: i64 = data.parse::<i64>().unwrap_or(0);
+            update_quantity(oid, qty)
+        }
+        UpdateType::Release => propose(f_order, role, PayoutType::Release, data).await,
+        UpdateType::Refund => propose(f_order, role, PayoutType::Refund, data).await,
+        UpdateType::Sign if role == Role::Mediator => {
+            if f_order.o_payout != PayoutType::Refund.value() {
+                error!("mediator can only co-sign refunds on order {}", oid);
+                return Default::default();
+            }
+            sign_with_market_key(f_order).await
+        }
+        UpdateType::Sign => co_sign(f_order, role, data).await,
+        UpdateType::RefundAddress if is_customer && (!status.is_some_and(|s| s.is_final()) || is_refund_due(&f_order)) => {
+            let u_order = update(oid, |o| {
+                if o.o_payout == PayoutType::Refund.value() {
+                    error!("refund for order {} is already proposed", &o.orid);
+                    return None;
+                }
+                Some(Order { o_refund_address: data, ..o })
+            });
+            // a refund due before the address was set can be proposed now
+            let due = is_cancel_agreed(&u_order) || is_refund_due(&u_order);
+            if due && u_order.o_payout == utils::empty_string() {
+                return propose_with_market_key(u_order, PayoutType::Refund).await;
+            }
+            u_order
+        }
+        UpdateType::Cancel => cancel(f_order, role).await,
+        _ => {
+            error!("{} can't make {:?} update on order {}", role.value(), update_type, oid);
+            Default::default()
+        }
+    }
+}
+
+/// Swap the reservation for the new quantity and reprice at the locked rate.
+/// The status is checked on the current record in the same transaction.
+fn update_quantity(oid: &String, qty: i64) -> Order {
+    let f_order: Order = find(oid);
+    let m_product: Product = product::find(&f_order.p_id);
+    let m_shipping: Shipping = shipping::find(&f_order.o_shipping);
+    if qty <= 0 || m_shipping.sid == utils::empty_string() {
+        return Default::default();
+    }
+    let m_commission = commission::find(&m_product.category);
+    update_with_stock(oid, |o| {
+        if OrderStatus::from_value(&o.o_status) != Some(OrderStatus::Created) {
+            error!("order {} is no longer {}", oid, OrderStatus::Created.value());
+            return None;
+        }
+        let o_amount = match get_amount(&m_product, &o.o_variant, &m_shipping, qty, o.o_rate) {
+            Some(a) => a,
+            None => {
+                error!("order amount for {} units of product {} is too large", qty, &o.p_id);
+                return None;
+            }
+        };
+        // orders created without a commission address stay commission free
+        let o_fee = if o.o_fee_address == utils::empty_string() {
+            0
+        } else {
+            commission::get_fee(&m_commission, o_amount)
+        };
+        let changes = vec![(o.o_quantity, product::StockChange::Release), (qty, product::StockChange::Reserve)];
+        Some((Order { o_quantity: qty, o_amount, o_fee, ..o }, changes))
+    })
+}
+
+/// Attempt to update prepare and make multisig info for the app
+async fn prepare_and_make(o: Order) -> Order {
+    let orid = String::from(&o.orid);
+    let info: Vec<String> = vec![String::from(&o.o_vend_msig_info), String::from(&o.o_cust_msig_info)];
+    let made = wallet::with(&orid, async {
+        let app_prepare: reqres::XmrRpcPrepareResponse = monero::prepare_wallet().await?;
+        let make: reqres::XmrRpcMakeResponse = monero::make_wallet(info, THRESHOLD).await?;
+        Ok((app_prepare, make))
+    });
+    let (app_prepare, make) = match made.await {
+        Ok(m) => m,
+        Err(e) => {
+            error!("error making multisig wallet {}: {}", &orid, e);
+            return o;
+        }
+    };
+    info!("prepared and made multisig wallet {}", &orid);
+    update(&orid, |u| {
+        Some(Order {
+            o_msig_prepare: app_prepare.result.multisig_info,
+            o_msig_make: make.result.address,
+            o_msig_kex: vec![make.result.multisig_info],
+            ..u
+        })
+    })
+}
+
+/// A party can send its message for a round once the app has sent
+/// the previous one. Round 0 is the make_multisig output.
+pub fn can_submit_kex(party: &[String], app: &[String]) -> bool {
+    !app.is_empty() && party.len() <= app.len()
+}
+
+/// Key exchange round the app can run, i.e. both parties sent their
+/// message for the app's latest round and the wallet isn't ready yet
+pub fn next_round(o: &Order) -> Option<usize> {
+    if o.o_msig_kex.is_empty() || o.o_xmr_address != utils::empty_string() {
+        return None;
+    }
+    let round = o.o_msig_kex.len() - 1;
+    (o.o_cust_kex.len() > round && o.o_vend_kex.len() > round).then_some(round)
+}
+
+/// Run a key exchange round for the app wallet. Once the wallet reports
+/// multisig is ready the order is ready for funding.
+async fn exchange_keys(o: Order, round: usize) -> Order {
+    let orid = String::from(&o.orid);
+    let info: Vec<String> = vec![String::from(&o.o_vend_kex[round]), String::from(&o.o_cust_kex[round])];
+    let exchanged = wallet::with(&orid, async {
+        let exchange: reqres::XmrRpcExchangeKeysResponse = monero::exchange_multisig_keys(info).await?;
+        let m_status: reqres::XmrRpcIsMultisigResponse = monero::is_multisig().await?;
+        Ok((exchange, m_status))
+    });
+    let (exchange, m_status) = match exchanged.await {
+        Ok(e) => e,
+        Err(e) => {
+            error!("error exchanging keys for wallet {} round {}: {}", &orid, round, e);
+            return o;
+        }
+    };
+    let is_escrow = m_status.result.threshold == THRESHOLD && m_status.result.total == PARTICIPANTS;
+    if m_status.result.ready && !is_escrow {
+        error!("wallet {} is {}/{} multisig", &orid, m_status.result.threshold, m_status.result.total);
+        return o;
+    }
+    let ready = m_status.result.ready && exchange.result.address != utils::empty_string();
+    if !ready && exchange.result.multisig_info == utils::empty_string() {
+        error!("error exchanging keys for wallet {} round {}", &orid, round);
+        return o;
+    }
+    info!("exchanged keys for wallet {} round {}", &orid, round);
+    let u_order = update(&orid, |u| {
+        let mut o_msig_kex = u.o_msig_kex.clone();
+        if exchange.result.multisig_info != utils::empty_string() {
+            o_msig_kex.push(exchange.result.multisig_info);
+        }
+        let o_xmr_address = if ready { exchange.result.address } else { u.o_xmr_address.clone() };
+        Some(Order { o_msig_kex, o_xmr_address, ..u })
+    });
+    if !ready {
+        return u_order;
+    }
+    set_status(&orid, OrderStatus::MultisigReady, Role::System)
+}
+
+/// Add a role to a list of roles such as the payout signers, each role appears once
+pub fn add_role(roles: &[String], role: Role) -> Vec<String> {
+    let mut u_roles = roles.to_vec();
+    if !u_roles.contains(&role.value()) {
+        u_roles.push(role.value());
+    }
+    u_roles
+}
+
+/// Both the customer and vendor agreed to cancel a funded order
+pub fn is_cancel_agreed(o: &Order) -> bool {
+    o.o_cancel.contains(&Role::Customer.value()) && o.o_cancel.contains(&Role::Vendor.value())
+}
+
+/// Cancel an unfunded order, releasing its stock. A funded order needs
+/// both parties to agree, then the market proposes a refund to the
+/// customer for either of them to co-sign.
+async fn cancel(o: Order, role: Role) -> Order {
+    let status = OrderStatus::from_value(&o.o_status);
+    // what an underpaid order received can't be refunded once it is cancelled
+    if status.is_some_and(|s| s.is_unfunded()) && o.o_received > 0 {
+        error!("order {} has received {} and can't be cancelled", &o.orid, o.o_received);
+        return Default::default();
+    }
+    if status.is_some_and(|s| s.is_unfunded()) {
+        return set_status(&o.orid, OrderStatus::Cancelled, role);
+    }
+    if status != Some(OrderStatus::Funded) {
+        error!("order {} can't be cancelled once {}", &o.orid, &o.o_status);
+        return Default::default();
+    }
+    info!("{} agreed to cancel order {}", role.value(), &o.orid);
+    let u_order = update(&o.orid, |u| {
+        if u.o_status != OrderStatus::Funded.value() {
+            return None;
+        }
+        Some(Order { o_cancel: add_role(&u.o_cancel, role), ..u })
+    });
+    if !is_cancel_agreed(&u_order) || u_order.o_payout != utils::empty_string() {
+        return u_order;
+    }
+    propose_with_market_key(u_order, PayoutType::Refund).await
+}
+
+/// Expired after receiving part of the amount, which isn't refunded yet
+pub fn is_refund_due(o: &Order) -> bool {
+    o.o_status == OrderStatus::Expired.value() && o.o_received > 0 && o.o_payout_hash == utils::empty_string()
+}
+
+/// Any two of customer, vendor and market can spend from the order wallet
+pub fn is_signed(o: &Order) -> bool {
+    o.o_msig_txset != utils::empty_string() && o.o_signers.len() >= THRESHOLD as usize
+}
+
+/// Addresses a payout of this type must pay with each one's percentage.
+/// A split pays the vendor `split` percent and refunds the rest.
+pub fn get_payout_shares(o: &Order, payout: PayoutType, split: u8) -> Vec<(String, u8)> {
+    let vendor = String::from(&o.o_subaddress);
+    let customer = String::from(&o.o_refund_address);
+    match payout {
+        PayoutType::Release => vec![(vendor, 100)],
+        PayoutType::Refund => vec![(customer, 100)],
+        PayoutType::Split => vec![(vendor, split), (customer, 100 - split.min(100))],
+    }
+}
+
+/// Payout shares, a split takes its terms from the order's dispute
+fn get_shares(o: &Order, payout: PayoutType) -> Vec<(String, u8)> {
+    let split = if payout == PayoutType::Split { dispute::find(&o.orid).split } else { 0 };
+    get_payout_shares(o, payout, split)
+}
+
+/// Commission a payout of this type must pay the market. A release pays
+/// it in full, a split on the vendor's `split` percent, a refund nothing.
+pub fn get_payout_commission(o: &Order, payout: PayoutType, split: u8) -> Option<reqres::Destination> {
+    let amount = match payout {
+        PayoutType::Release => o.o_fee,
+        PayoutType::Split => o.o_fee * split.min(100) as i64 / 100,
+        PayoutType::Refund => 0,
+    };
+    (amount > 0).then(|| reqres::Destination { address: String::from(&o.o_fee_address), amount: amount as u128 })
+}
+
+/// Payout commission, a split takes its terms from the order's dispute
+pub fn get_commission(o: &Order, payout: PayoutType) -> Option<reqres::Destination> {
+    let split = if payout == PayoutType::Split { dispute::find(&o.orid).split } else { 0 };
+    get_payout_commission(o, payout, split)
+}
+
+/// Every share has an address to pay and something to receive
+fn has_payout_addresses(shares: &[(String, u8)]) -> bool {
+    !shares.is_empty() && shares.iter().all(|(address, pct)| address != &utils::empty_string() && *pct > 0)
+}
+
+/// Divide an amount by the shares, the last one gets any rounding remainder
+pub fn get_destinations(shares: &[(String, u8)], amount: u128) -> Vec<reqres::Destination> {
+    let mut remaining = amount;
+    shares
+        .iter()
+        .enumerate()
+        .map(|(i, (address, pct))| {
+            let share = if i + 1 == shares.len() { remaining } else { amount * *pct as u128 / 100 };
+            remaining -= share;
+            reqres::Destination { address: String::from(address), amount: share }
+        })
+        .collect()
+}
+
+/// Destinations paying `amount` by the shares plus the commission, which
+/// comes out of the vendor's share. None if any of them would get nothing.
+pub fn get_payout_destinations(
+    o: &Order,
+    shares: &[(String, u8)],
+    amount: u128,
+    commission: Option<reqres::Destination>,
+) -> Option<Vec<reqres::Destination>> {
+    let mut destinations = get_destinations(shares, amount);
+    if let Some(c) = commission {
+        let vendor = destinations.iter_mut().find(|d| d.address == o.o_subaddress)?;
+        vendor.amount = vendor.amount.saturating_sub(c.amount);
+        destinations.push(c);
+    }
+    destinations.iter().all(|d| d.amount > 0).then_some(destinations)
+}
+
+/// Every output of the txset pays a share address or the commission, and
+/// each share address gets its percentage within one point since the fee
+/// comes out of those outputs. The commission is paid exactly and counts
+/// towards the vendor's share. Anything left over stays in the order wallet.
+pub fn is_valid_payout(
+    o: &Order,
+    desc: &[reqres::TransferDescription],
+    shares: &[(String, u8)],
+    commission: Option<&reqres::Destination>,
+) -> bool {
+    let is_commission = |r: &reqres::Destination| commission.is_some_and(|c| c.address == r.address);
+    let commission_paid: u128 = desc.iter().flat_map(|d| &d.recipients).filter(|r| is_commission(r)).map(|r| r.amount).sum();
+    let recipients: Vec<&reqres::Destination> =
+        desc.iter().flat_map(|d| &d.recipients).filter(|r| !is_commission(r)).collect();
+    let total: u128 = recipients.iter().map(|r| r.amount).sum::<u128>() + commission_paid;
+    let in_proportion = shares.iter().all(|(address, pct)| {
+        let mut paid: u128 = recipients.iter().filter(|r| &r.address == address).map(|r| r.amount).sum();
+        if address == &o.o_subaddress {
+            paid += commission_paid;
+        }
+        (paid * 100).abs_diff(total * *pct as u128) <= total
+    });
+    !desc.is_empty()
+        && !shares.is_empty()
+        && total > 0
+        && in_proportion
+        && commission.is_none_or(|c| commission_paid == c.amount)
+        && desc.iter().all(|d| {
+            !d.recipients.is_empty()
+                && d.recipients.iter().all(|r| {
+                    (shares.iter().any(|(a, _)| a == &r.address) || is_commission(r)) && r.amount > 0
+                })
+                && (d.change_amount == 0 || d.change_address == o.o_xmr_address)
+        })
+}
+
+/// Decode a txset with the order wallet
+async fn describe(orid: &str, txset: &str) -> Vec<reqres::TransferDescription> {
+    match wallet::with(orid, monero::describe_transfer(String::from(txset))).await {
+        Ok(describe) => describe.result.desc,
+        Err(e) => {
+            error!("error describing txset for order {}: {}", orid, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Start a payout with a txset signed by the proposer. The txset must pay
+/// the vendor subaddress for a release or the customer for a refund, and
+/// replaces any earlier proposal.
+async fn propose(o: Order, role: Role, payout: PayoutType, txset: String) -> Order {
+    let orid = String::from(&o.orid);
+    let status = OrderStatus::from_value(&o.o_status);
+    if !status.is_some_and(|s| can_transition(s, payout.status(), Role::System)) {
+        error!("order {} can't be paid out as a {}", &orid, payout.value());
+        return Default::default();
+    }
+    let shares = get_shares(&o, payout);
+    if !has_payout_addresses(&shares) {
+        error!("order {} has no {} address", &orid, payout.value());
+        return Default::default();
+    }
+    let desc = describe(&orid, &txset).await;
+    if !is_valid_payout(&o, &desc, &shares, get_commission(&o, payout).as_ref()) {
+        error!("txset for order {} doesn't pay the {} address", &orid, payout.value());
+        return Default::default();
+    }
+    info!("{} proposed a {} for order {}", role.value(), payout.value(), &orid);
+    update(&orid, |u| {
+        if u.o_status != o.o_status {
+            return None;
+        }
+        let o_signers = add_role(&[], role);
+        Some(Order { o_msig_txset: txset, o_signers, o_payout: payout.value(), ..u })
+    })
+}
+
+/// Add the second signature to the proposed payout and submit it
+async fn co_sign(o: Order, role: Role, txset: String) -> Order {
+    let orid = String::from(&o.orid);
+    let payout = match PayoutType::from_value(&o.o_payout) {
+        Some(p) => p,
+        None => {
+            error!("order {} has no payout to sign", &orid);
+            return Default::default();
+        }
+    };
+    if o.o_signers.contains(&role.value()) {
+        error!("{} already signed the payout for order {}", role.value(), &orid);
+        return Default::default();
+    }
+    let desc = describe(&orid, &txset).await;
+    if !is_valid_payout(&o, &desc, &get_shares(&o, payout), get_commission(&o, payout).as_ref()) {
+        error!("signed txset for order {} doesn't match the proposal", &orid);
+        return Default::default();
+    }
+    let u_order = update(&orid, |u| {
+        if u.o_payout != o.o_payout || u.o_signers != o.o_signers {
+            return None;
+        }
+        let o_signers = add_role(&u.o_signers, role);
+        Some(Order { o_msig_txset: txset, o_signers, ..u })
+    });
+    if u_order.orid == utils::empty_string() {
+        return u_order;
+    }
+    submit_payout(u_order).await
+}
+
+/// Broadcast a fully signed payout, record the tx hash and close out the order
+pub async fn submit_payout(o: Order) -> Order {
+    let orid = String::from(&o.orid);
+    let payout = match PayoutType::from_value(&o.o_payout) {
+        Some(p) if is_signed(&o) => p,
+        _ => return o,
+    };
+    let submit: reqres::XmrRpcSubmitMultisigResponse =
+        match wallet::with(&orid, monero::submit_multisig(String::from(&o.o_msig_txset))).await {
+            Ok(s) => s,
+            Err(e) => {
+                error!("error submitting payout for order {}: {}", &orid, e);
+                return o;
+            }
+        };
+    let o_payout_hash = submit.result.tx_hash_list.join(",");
+    info!("submitted {} for order {}: {}", payout.value(), &orid, &o_payout_hash);
+    let disputed = o.o_status == OrderStatus::Disputed.value();
+    let commission = get_commission(&o, payout);
+    // a split only paid the commission on the vendor's share
+    let o_fee = commission.as_ref().map_or(o.o_fee, |c| c.amount as i64);
+    // the payout is on chain, keep the hash even if the status can't change
+    update(&orid, |u| Some(Order { o_payout_hash, o_fee, ..u }));
+    let u_order = set_status(&orid, payout.status(), Role::System);
+    if u_order.orid != utils::empty_string() && commission.is_some() {
+        commission::record(&u_order);
+    }
+    if disputed && u_order.orid != utils::empty_string() {
+        dispute::settle(&orid, payout);
+    }
+    u_order
+}
+
+/// Propose a payout of the whole unlocked balance from the app's order
+/// wallet, signed with the market key. Used when the mediator or a
+/// background job starts the payout. The commission is paid exactly and
+/// the network fee is taken from the other outputs.
+pub async fn propose_with_market_key(o: Order, payout: PayoutType) -> Order {
+    let orid = String::from(&o.orid);
+    let shares = get_shares(&o, payout);
+    let commission = get_commission(&o, payout);
+    if !has_payout_addresses(&shares) {
+        error!("order {} has no {} address", &orid, payout.value());
+        return o;
+    }
+    let proposed = wallet::with(&orid, async {
+        let balance: reqres::XmrRpcBalanceResponse = monero::get_balance(vec![0]).await?;
+        let destinations = match get_payout_destinations(&o, &shares, balance.result.unlocked_balance, commission) {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+        let subtract_fee_from: Vec<u32> = (0..shares.len() as u32).collect();
+        monero::transfer(destinations, subtract_fee_from).await.map(Some)
+    });
+    let transfer: reqres::XmrRpcTransferResponse = match proposed.await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            error!("order {} has no unlocked balance for every {} output", &orid, payout.value());
+            return o;
+        }
+        Err(e) if e.wallet_error() == Some(rpc::WalletError::NotEnoughMoney) => {
+            error!("order {} can't pay the network fee for the {}", &orid, payout.value());
+            return o;
+        }
+        Err(e) => {
+            error!("error creating {} for order {}: {}", payout.value(), &orid, e);
+            return o;
+        }
+    };
+    info!("market proposed a {} for order {}", payout.value(), &orid);
+    update(&orid, |u| {
+        let o_signers = add_role(&[], Role::Mediator);
+        Some(Order { o_msig_txset: transfer.result.multisig_txset, o_signers, o_payout: payout.value(), ..u })
+    })
+}
+
+/// Sign the payout txset with the market key held in the app's order wallet
+pub async fn sign_with_market_key(o: Order) -> Order {
+    let orid = String::from(&o.orid);
+    if o.o_msig_txset == utils::empty_string() || o.o_signers.contains(&Role::Mediator.value()) {
+        return o;
+    }
+    let signed: reqres::XmrRpcSignMultisigResponse =
+        match wallet::with(&orid, monero::sign_multisig(String::from(&o.o_msig_txset))).await {
+            Ok(s) => s,
+            Err(e) => {
+                error!("error signing txset for order {}: {}", &orid, e);
+                return o;
+            }
+        };
+    info!("signed txset for order {} with the market key", &orid);
+    let u_order = update(&orid, |u| {
+        let o_signers = add_role(&u.o_signers, Role::Mediator);
+        Some(Order { o_msig_txset: signed.result.tx_data_hex, o_signers, ..u })
+    });
+    submit_payout(u_order).await
+}
+
+/// Used to update key images after funding the multisig wallet
+async fn update_multisig_info(o: Order) -> Order {
+    let orid = String::from(&o.orid);
+    let info: Vec<String> = vec![String::from(&o.o_vend_msig_export), String::from(&o.o_cust_msig_export)];
+    let exported = wallet::with(&orid, async {
+        let import: reqres::XmrRpcImportResponse = monero::import_multisig_info(info).await?;
+        if import.result.n_outputs == 0 {
+            warn!("no outputs imported for wallet {}", &orid);
+        }
+        monero::export_multisig_info().await
+    });
+    let export: reqres::XmrRpcExportResponse = match exported.await {
+        Ok(e) => e,
+        Err(e) => {
+            error!("error updating multisig info for wallet {}: {}", &orid, e);
+            return o;
+        }
+    };
+    update(&orid, |u| Some(Order { o_msig_export: export.result.info, ..u }))
+}
+
+/// Compare what the order wallet received with the order amount. Only
+/// transfers with enough confirmations count towards funding.
+pub fn get_funding(amount: i64, transfers: &[reqres::TransferEntry], confirmations: u64) -> (FundingStatus, i64) {
+    let expected = amount.max(0) as u128;
+    let received: u128 = transfers.iter().map(|t| t.amount).sum();
+    let confirmed: u128 = transfers
+        .iter()
+        .filter(|t| t.confirmations >= confirmations)
+        .map(|t| t.amount)
+        .sum();
+    let status = if received == 0 {
+        FundingStatus::Unpaid
+    } else if received < expected {
+        FundingStatus::Underpaid
+    } else if confirmed < expected {
+        FundingStatus::Pending
+    } else if received > expected {
+        FundingStatus::Overpaid
+    } else {
+        FundingStatus::Paid
+    };
+    (status, received as i64)
+}
+
+/// Orders that can still change status
+pub fn find_open() -> Vec<Order> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_open_key());
+    split_list(r)
+        .iter()
+        .map(find)
+        .filter(|o| o.orid != utils::empty_string())
+        .collect()
+}
+
+/// Check an order wallet for incoming transfers, record what arrived and
+/// move the order to funded once the amount is confirmed
+pub async fn check_funding(o: Order) -> Order {
+    let orid = String::from(&o.orid);
+    let checked = wallet::with(&orid, async {
+        let transfers: reqres::XmrRpcGetTransfersResponse = monero::get_transfers().await?;
+        let balance: reqres::XmrRpcBalanceResponse = monero::get_balance(vec![0]).await?;
+        Ok((transfers, balance))
+    });
+    let (transfers, balance) = match checked.await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("error checking funding of order {}: {}", &orid, e);
+            return o;
+        }
+    };
+    let mut m_transfers = transfers.result.incoming;
+    m_transfers.extend(transfers.result.pool);
+    let (funding, received) = get_funding(o.o_amount, &m_transfers, get_confirmations());
+    debug!("order {} received {} of {}, wallet balance {}", &orid, received, o.o_amount, balance.result.balance);
+    match funding {
+        FundingStatus::Underpaid => warn!("order {} is underpaid: {} of {}", &orid, received, o.o_amount),
+        FundingStatus::Overpaid => warn!("order {} is overpaid: {} of {}", &orid, received, o.o_amount),
+        _ => (),
+    }
+    let txids: Vec<String> = m_transfers.iter().map(|t| String::from(&t.txid)).collect();
+    if o.o_hash != utils::empty_string() && !txids.contains(&o.o_hash) {
+        warn!("reported funding tx {} not found for order {}", &o.o_hash, &orid);
+    }
+    let u_order = update(&orid, |u| {
+        if u.o_status != OrderStatus::MultisigReady.value() {
+            return None;
+        }
+        let o_hash = if u.o_hash == utils::empty_string() {
+            txids.first().cloned().unwrap_or_default()
+        } else {
+            u.o_hash.clone()
+        };
+        Some(Order { o_funding: funding.value(), o_received: received, o_hash, ..u })
+    });
+    match funding {
+        FundingStatus::Paid | FundingStatus::Overpaid if u_order.orid != utils::empty_string() => {
+            info!("order {} is funded", &orid);
+            set_status(&orid, OrderStatus::Funded, Role::System)
+        }
+        _ => u_order,
+    }
+}
+
+/// Background job checking every order with a ready wallet for funding
+pub async fn check_all_funding() {
+    let orders: Vec<Order> = find_open()
+        .into_iter()
+        .filter(|o| o.o_status == OrderStatus::MultisigReady.value())
+        .collect();
+    debug!("checking {} orders for funding", orders.len());
+    for o in orders {
+        check_funding(o).await;
+    }
+}
+
+/// Last time the order moved to the status, zero if it never did
+pub fn get_status_date(o: &Order, status: OrderStatus) -> i64 {
+    o.o_history
+        .iter()
+        .rev()
+        .find(|c| c.status == status.value())
+        .map(|c| c.timestamp)
+        .unwrap_or(0)
+}
+
+/// Deadline rule for an order, if one is due
+pub fn get_deadline_action(o: &Order, d: &Deadlines, now: i64) -> Option<DeadlineAction> {
+    match OrderStatus::from_value(&o.o_status)? {
+        s if s.is_unfunded() => (now - o.o_date > d.expire).then_some(DeadlineAction::Expire),
+        OrderStatus::Funded => {
+            let refund = o.o_payout == PayoutType::Refund.value() && o.o_signers == vec![Role::Customer.value()];
+            let late = now - get_status_date(o, OrderStatus::Funded) > d.ship;
+            (refund && late).then_some(DeadlineAction::Refund)
+        }
+        OrderStatus::Shipped => (now - o.o_ship_date > d.finalize).then_some(DeadlineAction::Finalize),
+        OrderStatus::Delivered => {
+            let finalized = o.o_history.last().is_some_and(|c| c.role == Role::System.value());
+            // the vendor can't get paid without a proposal to co-sign
+            let late = now - get_status_date(o, OrderStatus::Delivered) > d.finalize;
+            ((finalized || late) && o.o_payout == utils::empty_string()).then_some(DeadlineAction::ProposeRelease)
+        }
+        OrderStatus::Expired => {
+            (is_refund_due(o) && o.o_payout == utils::empty_string()).then_some(DeadlineAction::ProposeRefund)
+        }
+        _ => None,
+    }
+}
+
+/// Background job applying the order deadlines
+pub async fn enforce_deadlines() {
+    let d = get_deadlines();
+    let now = chrono::offset::Utc::now().timestamp();
+    for o in find_open() {
+        let action = match get_deadline_action(&o, &d, now) {
+            Some(a) => a,
+            None => continue,
+        };
+        info!("order {} deadline reached: {:?}", &o.orid, action);
+        match action {
+            DeadlineAction::Expire => {
+                let u_order = set_status(&o.orid, OrderStatus::Expired, Role::System);
+                if is_refund_due(&u_order) {
+                    propose_with_market_key(u_order, PayoutType::Refund).await;
+                }
+            }
+            DeadlineAction::Refund => {
+                sign_with_market_key(o).await;
+            }
+            DeadlineAction::Finalize => {
+                let u_order = set_status(&o.orid, OrderStatus::Delivered, Role::System);
+                if u_order.orid != utils::empty_string() {
+                    propose_with_market_key(u_order, PayoutType::Release).await;
+                }
+            }
+            DeadlineAction::ProposeRelease => {
+                propose_with_market_key(o, PayoutType::Release).await;
+            }
+            DeadlineAction::ProposeRefund => {
+                propose_with_market_key(o, PayoutType::Refund).await;
+            }
+        }
+    }
+}
+
+/// Only the customer, vendor or mediator can see an order
+pub fn find_for(oid: &String, address: &String) -> Order {
+    let f_order: Order = find(oid);
+    if f_order.orid == utils::empty_string() || get_role(&f_order, address).is_none() {
+        return Default::default();
+    }
+    f_order
+}
+
+/// Lookup all orders for the customer or vendor owning the address
+pub fn find_all_for(cvid: &String, address: &String) -> Vec<Order> {
+    let is_customer = &customer::find(cvid).xmr_address == address;
+    let is_vendor = &vendor::find(cvid).v_xmr_address == address;
+    if !is_customer && !is_vendor {
+        error!("{} can't list orders for {}", address, cvid);
+        return Vec::new();
+    }
+    find_all(cvid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_test() {
+        let o = Order { o_status: OrderStatus::Funded.value(), ..Default::default() };
+        // only the vendor ships
+        assert!(transition(o.clone(), OrderStatus::Shipped, Role::Customer, 10).is_none());
+        let shipped = transition(o, OrderStatus::Shipped, Role::Vendor, 10).unwrap();
+        assert_eq!(shipped.o_status, OrderStatus::Shipped.value());
+        assert_eq!(shipped.o_ship_date, 10);
+        let delivered = transition(shipped, OrderStatus::Delivered, Role::Customer, 20).unwrap();
+        assert_eq!(delivered.o_deliver_date, 20);
+        assert_eq!(delivered.o_history.len(), 2);
+        assert_eq!(delivered.o_history[1].role, Role::Customer.value());
+        assert!(transition(delivered, OrderStatus::Cancelled, Role::Customer, 30).is_none());
+    }
+
+    #[test]
+    fn next_round_test() {
+        let msg = |m: &str| String::from(m);
+        let o = Order { o_msig_kex: vec![msg("app0")], o_cust_kex: vec![msg("c0")], ..Default::default() };
+        assert_eq!(next_round(&o), None);
+        let o = Order { o_vend_kex: vec![msg("v0")], ..o };
+        assert_eq!(next_round(&o), Some(0));
+        let o = Order { o_msig_kex: vec![msg("app0"), msg("app1")], ..o };
+        assert_eq!(next_round(&o), None);
+        let o = Order { o_xmr_address: msg("address"), ..o };
+        assert_eq!(next_round(&o), None);
+    }
+
+    #[test]
+    fn signers_test() {
+        let signers = add_role(&[], Role::Customer);
+        let signers = add_role(&signers, Role::Customer);
+        let o = Order { o_msig_txset: String::from("txset"), o_signers: signers, ..Default::default() };
+        assert!(!is_signed(&o));
+        let o = Order { o_signers: add_role(&o.o_signers, Role::Mediator), ..o };
+        assert!(is_signed(&o));
+    }
+
+    #[test]
+    fn is_valid_payout_test() {
+        let o = Order {
+            o_xmr_address: String::from("msig"),
+            o_subaddress: String::from("vendor"),
+            ..Default::default()
+        };
+        let shares = get_payout_shares(&o, PayoutType::Release, 0);
+        let desc = |to: &str, change_address: &str, change_amount: u128| reqres::TransferDescription {
+            amount_in: 100,
+            amount_out: 90,
+            recipients: vec![reqres::Destination { address: String::from(to), amount: 90 }],
+            change_address: String::from(change_address),
+            change_amount,
+            fee: 1,
+        };
+        assert!(is_valid_payout(&o, &[desc("vendor", "", 0)], &shares, None));
+        assert!(is_valid_payout(&o, &[desc("vendor", "msig", 9)], &shares, None));
+        assert!(!is_valid_payout(&o, &[desc("vendor", "other", 9)], &shares, None));
+        assert!(!is_valid_payout(&o, &[desc("other", "", 0)], &shares, None));
+        assert!(!is_valid_payout(&o, &[], &shares, None));
+        assert!(!has_payout_addresses(&get_payout_shares(&o, PayoutType::Refund, 0)));
+    }
+
+    #[test]
+    fn split_payout_test() {
+        let o = Order {
+            o_xmr_address: String::from("msig"),
+            o_subaddress: String::from("vendor"),
+            o_refund_address: String::from("customer"),
+            ..Default::default()
+        };
+        let shares = get_payout_shares(&o, PayoutType::Split, 70);
+        assert!(has_payout_addresses(&shares));
+        assert!(!has_payout_addresses(&get_payout_shares(&o, PayoutType::Split, 100)));
+        let destinations = get_destinations(&shares, 1001);
+        assert_eq!(destinations.iter().map(|d| d.amount).collect::<Vec<u128>>(), vec![700, 301]);
+        let desc = |vendor: u128, customer: u128| reqres::TransferDescription {
+            amount_in: 1001,
+            amount_out: vendor + customer,
+            recipients: vec![
+                reqres::Destination { address: String::from("vendor"), amount: vendor },
+                reqres::Destination { address: String::from("customer"), amount: customer },
+            ],
+            change_address: utils::empty_string(),
+            change_amount: 0,
+            fee: 1001 - vendor - customer,
+        };
+        // the fee is taken from both outputs
+        assert!(is_valid_payout(&o, &[desc(695, 296)], &shares, None));
+        assert!(!is_valid_payout(&o, &[desc(500, 491)], &shares, None));
+        assert!(!is_valid_payout(&o, &[desc(991, 0)], &shares, None));
+        // the commission comes out of the vendor's share
+        let o = Order { o_fee: 100, o_fee_address: String::from("market"), ..o };
+        let commission = get_payout_commission(&o, PayoutType::Split, 70);
+        let destinations = get_payout_destinations(&o, &shares, 1001, commission.clone()).unwrap();
+        assert_eq!(destinations.iter().map(|d| d.amount).collect::<Vec<u128>>(), vec![630, 301, 70]);
+        let with_market = |vendor: u128, customer: u128| reqres::TransferDescription {
+            recipients: vec![
+                reqres::Destination { address: String::from("vendor"), amount: vendor },
+                reqres::Destination { address: String::from("customer"), amount: customer },
+                reqres::Destination { address: String::from("market"), amount: 70 },
+            ],
+            ..desc(vendor, customer)
+        };
+        assert!(is_valid_payout(&o, &[with_market(625, 296)], &shares, commission.as_ref()));
+        assert!(!is_valid_payout(&o, &[with_market(695, 226)], &shares, commission.as_ref()));
+        assert!(get_payout_destinations(&o, &shares, 100, commission).is_none());
+    }
+
+    #[test]
+    fn commission_payout_test() {
+        let o = Order {
+            o_xmr_address: String::from("msig"),
+            o_subaddress: String::from("vendor"),
+            o_fee: 10,
+            o_fee_address: String::from("market"),
+            ..Default::default()
+        };
+        assert!(get_commission(&o, PayoutType::Refund).is_none());
+        assert_eq!(get_payout_commission(&o, PayoutType::Split, 70).map(|c| c.amount), Some(7));
+        assert!(get_payout_commission(&o, PayoutType::Split, 0).is_none());
+        let commission = get_commission(&o, PayoutType::Release);
+        let shares = get_payout_shares(&o, PayoutType::Release, 0);
+        let desc = |market: u128| reqres::TransferDescription {
+            amount_in: 100,
+            amount_out: 89 + market,
+            recipients: vec![
+                reqres::Destination { address: String::from("vendor"), amount: 89 },
+                reqres::Destination { address: String::from("market"), amount: market },
+            ],
+            change_address: utils::empty_string(),
+            change_amount: 0,
+            fee: 1,
+        };
+        assert!(is_valid_payout(&o, &[desc(10)], &shares, commission.as_ref()));
+        assert!(!is_valid_payout(&o, &[desc(9)], &shares, commission.as_ref()));
+        // the commission address isn't a valid recipient without a commission
+        assert!(!is_valid_payout(&o, &[desc(10)], &shares, None));
+    }
+
+    #[test]
+    fn get_funding_test() {
+        let transfer = |amount: u128, confirmations: u64| reqres::TransferEntry {
+            amount,
+            confirmations,
+            ..Default::default()
+        };
+        assert_eq!(get_funding(100, &[], 10), (FundingStatus::Unpaid, 0));
+        assert_eq!(get_funding(100, &[transfer(60, 10)], 10), (FundingStatus::Underpaid, 60));
+        assert_eq!(get_funding(100, &[transfer(60, 10), transfer(40, 2)], 10), (FundingStatus::Pending, 100));
+        assert_eq!(get_funding(100, &[transfer(60, 12), transfer(40, 10)], 10), (FundingStatus::Paid, 100));
+        assert_eq!(get_funding(100, &[transfer(150, 10)], 10), (FundingStatus::Overpaid, 150));
+    }
+
+    #[test]
+    fn get_deadline_action_test() {
+        let d = Deadlines { expire: 10, ship: 20, finalize: 30 };
+        let changed = |status: OrderStatus, role: Role, timestamp: i64| StatusChange {
+            status: status.value(),
+            role: role.value(),
+            timestamp,
+        };
+        let created = Order { o_status: OrderStatus::MultisigReady.value(), o_date: 100, ..Default::default() };
+        assert_eq!(get_deadline_action(&created, &d, 105), None);
+        assert_eq!(get_deadline_action(&created, &d, 111), Some(DeadlineAction::Expire));
+        let underpaid = Order { o_received: 5, ..created };
+        assert_eq!(get_deadline_action(&underpaid, &d, 105), None);
+        assert_eq!(get_deadline_action(&underpaid, &d, 111), Some(DeadlineAction::Expire));
+        let expired = Order { o_status: OrderStatus::Expired.value(), ..underpaid };
+        assert_eq!(get_deadline_action(&expired, &d, 120), Some(DeadlineAction::ProposeRefund));
+        let refunding = Order { o_payout: PayoutType::Refund.value(), ..expired.clone() };
+        assert_eq!(get_deadline_action(&refunding, &d, 120), None);
+        assert_eq!(get_deadline_action(&Order { o_received: 0, ..expired }, &d, 120), None);
+        let funded = Order {
+            o_status: OrderStatus::Funded.value(),
+            o_history: vec![changed(OrderStatus::Funded, Role::System, 200)],
+            ..Default::default()
+        };
+        assert_eq!(get_deadline_action(&funded, &d, 300), None);
+        let refund = Order {
+            o_payout: PayoutType::Refund.value(),
+            o_signers: vec![Role::Customer.value()],
+            ..funded
+        };
+        assert_eq!(get_deadline_action(&refund, &d, 210), None);
+        assert_eq!(get_deadline_action(&refund, &d, 221), Some(DeadlineAction::Refund));
+        let shipped = Order { o_status: OrderStatus::Shipped.value(), o_ship_date: 300, ..Default::default() };
+        assert_eq!(get_deadline_action(&shipped, &d, 330), None);
+        assert_eq!(get_deadline_action(&shipped, &d, 331), Some(DeadlineAction::Finalize));
+        let disputed = Order { o_status: OrderStatus::Disputed.value(), ..shipped };
+        assert_eq!(get_deadline_action(&disputed, &d, 400), None);
+        let delivered = Order {
+            o_status: OrderStatus::Delivered.value(),
+            o_history: vec![changed(OrderStatus::Delivered, Role::System, 331)],
+            ..Default::default()
+        };
+        assert_eq!(get_deadline_action(&delivered, &d, 400), Some(DeadlineAction::ProposeRelease));
+        let by_customer = Order {
+            o_history: vec![changed(OrderStatus::Delivered, Role::Customer, 331)],
+            ..delivered
+        };
+        assert_eq!(get_deadline_action(&by_customer, &d, 361), None);
+        assert_eq!(get_deadline_action(&by_customer, &d, 362), Some(DeadlineAction::ProposeRelease));
+        // the customer's release proposal is left for the vendor to co-sign
+        let proposed = Order { o_payout: PayoutType::Release.value(), ..by_customer };
+        assert_eq!(get_deadline_action(&proposed, &d, 400), None);
+    }
+
+    #[test]
+    fn is_cancel_agreed_test() {
+        let o = Order { o_cancel: add_role(&[], Role::Customer), ..Default::default() };
+        assert!(!is_cancel_agreed(&o));
+        let o = Order { o_cancel: add_role(&o.o_cancel, Role::Vendor), ..o };
+        assert!(is_cancel_agreed(&o));
+    }
+
+    #[test]
+    fn can_submit_kex_test() {
+        let app = vec![String::from("app0")];
+        assert!(!can_submit_kex(&Vec::new(), &Vec::new()));
+        assert!(can_submit_kex(&Vec::new(), &app));
+        assert!(can_submit_kex(&[String::from("c0")], &app));
+        assert!(!can_submit_kex(&[String::from("c0"), String::from("c1")], &app));
+    }
+
+    #[test]
+    fn get_stock_change_test() {
+        use product::StockChange;
+        assert_eq!(get_stock_change(Some(OrderStatus::MultisigReady), OrderStatus::Funded), Some(StockChange::Commit));
+        assert_eq!(get_stock_change(Some(OrderStatus::Created), OrderStatus::Expired), Some(StockChange::Release));
+        assert_eq!(get_stock_change(Some(OrderStatus::MultisigReady), OrderStatus::Cancelled), Some(StockChange::Release));
+        assert_eq!(get_stock_change(Some(OrderStatus::Funded), OrderStatus::Refunded), Some(StockChange::Restock));
+        // shipped units don't come back
+        assert_eq!(get_stock_change(Some(OrderStatus::Disputed), OrderStatus::Refunded), None);
+        assert_eq!(get_stock_change(Some(OrderStatus::Funded), OrderStatus::Shipped), None);
+    }
+
+    #[test]
+    fn can_transition_test() {
+        use OrderStatus::*;
+        assert!(can_transition(MultisigReady, Funded, Role::System));
+        assert!(!can_transition(MultisigReady, Funded, Role::Customer));
+        assert!(can_transition(Created, Cancelled, Role::Customer));
+        assert!(!can_transition(Funded, Cancelled, Role::Customer));
+        assert!(can_transition(Disputed, Refunded, Role::Mediator));
+        assert!(!can_transition(Disputed, Released, Role::Vendor));
+        assert!(!can_transition(Created, Funded, Role::System));
+    }
+
+    #[test]
+    fn get_amount_test() {
+        let p = Product {
+            p_price: 100,
+            variants: vec![Variant { vrid: String::from("var1"), price: 250, ..Default::default() }],
+            ..Default::default()
+        };
+        let s = Shipping { price: 30, ..Default::default() };
+        assert_eq!(get_amount(&p, &utils::empty_string(), &s, 2, 0), Some(230));
+        assert_eq!(get_amount(&p, &String::from("var1"), &s, 2, 0), Some(530));
+        assert_eq!(get_amount(&p, &utils::empty_string(), &s, i64::MAX / 50, 0), None);
+        // 2 x 1.00 USD at 100.00 USD per xmr
+        let fiat = Product { currency: String::from("USD"), ..p };
+        let expected = rates::PICONERO / 50 + 30;
+        assert_eq!(get_amount(&fiat, &utils::empty_string(), &s, 2, 10000), Some(expected));
+        assert_eq!(get_amount(&fiat, &utils::empty_string(), &s, i64::MAX / 100, 1), None);
+    }
+}
@@ -1,145 +1,501 @@
-// use crate::models::*;
-// use crate::schema;
-// use crate::utils;
-// use diesel::prelude::*;
-// use log::{debug, error, info};
-
-// #[derive(Debug)]
-// enum UpdateType {
-//     InStock,
-//     Description,
-//     Name,
-//     Price,
-//     Quantity,
-// }
-
-// impl UpdateType {
-//     pub fn value(&self) -> i32 {
-//         match *self {
-//             UpdateType::InStock => 0,
-//             UpdateType::Description => 1,
-//             UpdateType::Name => 2,
-//             UpdateType::Price => 3,
-//             UpdateType::Quantity => 4,
-//         }
-//     }
-// }
-
-// /// Create a skeleton for the product
-// pub async fn create(v_id: String) -> Product {
-//     use crate::schema::products;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let f_pid: String = format!("P{}", utils::generate_rnd());
-//     let new_product = NewProduct {
-//         pid: &f_pid,
-//         v_id: &v_id,
-//         in_stock: &false,
-//         p_description: "",
-//         p_name: "",
-//         p_price: &0,
-//         qty: &0,
-//     };
-//     debug!("insert product: {:?}", new_product);
-//     diesel::insert_into(products::table)
-//         .values(&new_product)
-//         .get_result(connection)
-//         .expect("error saving new product")
-// }
-
-// /// Lookup product
-// pub async fn find(r_pid: String) -> Product {
-//     use self::schema::products::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let results = products
-//         .filter(schema::products::pid.eq(r_pid))
-//         .first::<Product>(connection);
-//     match results {
-//         Ok(r) => r,
-//         _ => {
-//             error!("error finding product");
-//             Default::default()
-//         }
-//     }
-// }
-
-// pub async fn modify(_id: String, data: String, update_type: i32) -> Product {
-//     use self::schema::products::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     // TODO: this isn't right. The product should automatically
-//     // get updated based on the qty. Qty should be updated according
-//     // to settled orders
-//     if update_type == UpdateType::InStock.value() {
-//         info!("modify product active status");
-//         let m = diesel::update(products.find(_id))
-//             .set(in_stock.eq(true))
-//             .get_result::<Product>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Description.value() {
-//         info!("modify product description");
-//         let m = diesel::update(products.find(_id))
-//             .set(p_description.eq(data))
-//             .get_result::<Product>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Name.value() {
-//         info!("modify product name");
-//         let m = diesel::update(products.find(_id))
-//             .set(p_name.eq(data))
-//             .get_result::<Product>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Price.value() {
-//         info!("modify product price");
-//         let price_data = match data.parse::<i64>() {
-//             Ok(n) => n,
-//             Err(_e) => 0,
-//         };
-//         let m = diesel::update(products.find(_id))
-//             .set(p_price.eq(price_data))
-//             .get_result::<Product>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     } else if update_type == UpdateType::Quantity.value() {
-//         info!("modify product quantity");
-//         let amt = match data.parse::<i64>() {
-//             Ok(n) => n,
-//             Err(_e) => 0,
-//         };
-//         let m = diesel::update(products.find(_id))
-//             .set(qty.eq(amt))
-//             .get_result::<Product>(connection);
-//         return match m {
-//             Ok(m) => m,
-//             Err(_e) => Default::default(),
-//         };
-//     }
-//     Default::default()
-// }
-
-// pub async fn find_all(_v_id: String) -> Vec<Product> {
-//     use self::schema::products::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let results = products
-//         .filter(schema::products::v_id.eq(_v_id))
-//         .load::<Product>(connection);
-//     match results {
-//         Ok(r) => {
-//             info!("found vendor products");
-//             r
-//         }
-//         _ => {
-//             error!("error finding vendor products");
-//             let v: Vec<Product> = Vec::new();
-//             v
-//         }
-//     }
-// }
+// Product repo/service layer
+use crate::{db, media, models::*, rates, reqres, search, utils, vendor};
+use log::{debug, error, info};
+
+/// Key for the list of pids belonging to a vendor
+fn get_list_key(vid: &String) -> String {
+    format!("pl{}", vid)
+}
+
+/// Stock changes made together with an order change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StockChange {
+    /// hold units for a new order
+    Reserve,
+    /// return held units when an unfunded order expires or is cancelled
+    Release,
+    /// funded order, held units leave the stock for good
+    Commit,
+    /// funded order refunded before shipping, units go back on hand
+    Restock,
+}
+
+fn write(p: &Product) {
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &p.pid);
+    db::Interface::write(&s.env, &s.handle, &p.pid, &Product::to_db(p));
+}
+
+/// Read, change and write the product in one transaction so concurrent
+/// stock changes are never lost. Records in `with` (e.g. the order) are
+/// written in the same transaction. Nothing is written when `f` returns None.
+fn update<F>(pid: &String, with: &[(String, String)], f: F) -> Product
+where
+    F: FnOnce(Product) -> Option<Product>,
+{
+    let s = db::Interface::open();
+    let mut u_product: Product = Default::default();
+    db::Interface::transaction(&s.env, &s.handle, |db| {
+        let r = String::from(db.get::<&str>(pid).unwrap_or(""));
+        if r == utils::empty_string() {
+            error!("error finding product");
+            return false;
+        }
+        let p = match f(Product::from_db(String::from(pid), r)) {
+            Some(p) => derive_in_stock(p),
+            None => return false,
+        };
+        let v = Product::to_db(&p);
+        let mut records: Vec<(&str, &str)> = vec![(pid.as_str(), v.as_str())];
+        records.extend(with.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        for (k, v) in records {
+            if db.set(&k, &v).is_err() {
+                error!("failed to write {}", k);
+                return false;
+            }
+        }
+        u_product = p;
+        true
+    });
+    u_product
+}
+
+/// Units of the product or variant not held by unfunded orders
+pub fn available(p: &Product, vrid: &String) -> i64 {
+    if vrid == &utils::empty_string() {
+        return p.qty - p.reserved;
+    }
+    let v: Variant = get_variant(p, vrid);
+    v.qty - v.reserved
+}
+
+/// In stock while the product, or any of its variants, has units available
+fn derive_in_stock(p: Product) -> Product {
+    let in_stock = if p.variants.is_empty() {
+        p.qty > p.reserved
+    } else {
+        p.variants.iter().any(|v| v.qty > v.reserved)
+    };
+    Product { in_stock, ..p }
+}
+
+/// Apply a stock change for `quantity` units, None if there is not enough stock
+pub fn apply_stock(p: Product, vrid: &String, quantity: i64, change: StockChange) -> Option<Product> {
+    let change_units = |qty: i64, reserved: i64| -> Option<(i64, i64)> {
+        match change {
+            StockChange::Reserve if qty - reserved >= quantity => Some((qty, reserved + quantity)),
+            StockChange::Release if reserved >= quantity => Some((qty, reserved - quantity)),
+            StockChange::Commit if reserved >= quantity => Some((qty - quantity, reserved - quantity)),
+            StockChange::Restock => Some((qty + quantity, reserved)),
+            _ => None,
+        }
+    };
+    if quantity <= 0 {
+        return None;
+    }
+    if vrid == &utils::empty_string() {
+        let (qty, reserved) = change_units(p.qty, p.reserved)?;
+        return Some(derive_in_stock(Product { qty, reserved, ..p }));
+    }
+    let f_variant: Variant = get_variant(&p, vrid);
+    if f_variant.vrid == utils::empty_string() {
+        return None;
+    }
+    let (qty, reserved) = change_units(f_variant.qty, f_variant.reserved)?;
+    let u_variant = Variant { qty, reserved, ..f_variant };
+    let variants: Vec<Variant> = p
+        .variants
+        .iter()
+        .map(|v| if &v.vrid == vrid { u_variant.clone() } else { v.clone() })
+        .collect();
+    Some(derive_in_stock(Product { variants, ..p }))
+}
+
+/// Change the stock for an order and write the order in the same transaction
+pub fn update_stock(o: &Order, change: StockChange) -> bool {
+    info!("{:?} {} units of product {} for order {}", change, o.o_quantity, o.p_id, o.orid);
+    let with = vec![(String::from(&o.orid), Order::to_db(o))];
+    let u_product = update(&o.p_id, &with, |p| apply_stock(p, &o.o_variant, o.o_quantity, change));
+    if u_product.pid == utils::empty_string() {
+        error!("insufficient stock of product {} for order {}", o.p_id, o.orid);
+        return false;
+    }
+    true
+}
+
+/// Apply the fields present in the request to the product
+fn apply(p: Product, r: &reqres::ProductRequest) -> Product {
+    Product {
+        p_description: r.description.clone().unwrap_or(p.p_description),
+        p_name: r.name.clone().unwrap_or(p.p_name),
+        p_price: r.price.unwrap_or(p.p_price),
+        qty: r.qty.unwrap_or(p.qty),
+        category: r.category.as_ref().map_or(p.category, |c| search::normalize(c)),
+        currency: r.currency.as_ref().map_or(p.currency, |c| rates::normalize(c)),
+        tags: r.tags.as_ref().map_or(p.tags, |t| {
+            t.iter().map(|tag| search::normalize(tag)).filter(|tag| !tag.is_empty()).collect()
+        }),
+        ..p
+    }
+}
+
+/// Prices are in piconero unless a valid currency code is set
+fn is_valid_currency(p: &Product) -> bool {
+    p.currency.is_empty() || rates::is_valid_currency(&p.currency)
+}
+
+/// Price and stock can't be negative and stock can't drop below what
+/// orders have reserved
+fn is_valid(p: &Product) -> bool {
+    p.p_price >= 0 && p.qty >= 0 && p.qty >= p.reserved && is_valid_currency(p)
+}
+
+/// Check that the address from the token claims owns the product
+pub fn is_owner(p: &Product, address: &String) -> bool {
+    let m_vendor: Vendor = vendor::find(&p.v_id);
+    m_vendor.vid != utils::empty_string() && &m_vendor.v_xmr_address == address
+}
+
+/// Create a product for an active vendor
+pub fn create(vid: &String, address: &String, r: &reqres::ProductRequest) -> Product {
+    let m_vendor: Vendor = vendor::find(vid);
+    if &m_vendor.v_xmr_address != address || !m_vendor.active {
+        error!("vendor {} can't create products", vid);
+        return Default::default();
+    }
+    let f_pid: String = format!("prod{}", utils::generate_rnd());
+    let skeleton = Product {
+        pid: f_pid,
+        v_id: String::from(vid),
+        ..Default::default()
+    };
+    let new_product = derive_in_stock(apply(skeleton, r));
+    if !is_valid(&new_product) {
+        error!("invalid product for vendor {}", vid);
+        return Default::default();
+    }
+    debug!("insert product: {:?}", &new_product);
+    write(&new_product);
+    search::index(&new_product);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_list_key(vid), &new_product.pid);
+    new_product
+}
+
+/// Lookup product
+pub fn find(pid: &String) -> Product {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &String::from(pid));
+    if r == utils::empty_string() {
+        error!("error finding product");
+        return Default::default()
+    }
+    Product::from_db(String::from(pid), r)
+}
+
+/// Lookup all products for a vendor
+pub fn find_all(vid: &String) -> Vec<Product> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key(vid));
+    if r == utils::empty_string() {
+        info!("no products found for vendor {}", vid);
+        return Vec::new()
+    }
+    r.split(',')
+        .map(|pid| find(&String::from(pid)))
+        .filter(|p| p.pid != utils::empty_string())
+        .collect()
+}
+
+/// Update product information, only the owning vendor may do this.
+/// `qty` is the stock on hand, it can't drop below what orders have reserved.
+pub fn modify(pid: &String, address: &String, r: &reqres::ProductRequest) -> Product {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} can't be modified by {}", pid, address);
+        return Default::default();
+    }
+    info!("modify product {}", pid);
+    let u_product = update(pid, &[], |p| {
+        let u = apply(p, r);
+        if is_valid(&u) { Some(u) } else { None }
+    });
+    if u_product.pid == utils::empty_string() {
+        error!("invalid update for product {}", pid);
+        return u_product;
+    }
+    search::unindex(&f_product);
+    search::index(&u_product);
+    u_product
+}
+
+/// Open orders hold units of the product or one of its variants
+pub fn has_reservations(p: &Product) -> bool {
+    p.reserved > 0 || p.variants.iter().any(|v| v.reserved > 0)
+}
+
+/// Delete a product, only the owning vendor may do this. Orders holding
+/// stock must settle first, they update the product when they do.
+pub fn delete(pid: &String, address: &String) -> bool {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} can't be deleted by {}", pid, address);
+        return false;
+    }
+    let s = db::Interface::open();
+    // checked in the delete transaction so no order reserves in between
+    let deleted = db::Interface::transaction(&s.env, &s.handle, |db| {
+        let r = String::from(db.get::<&str>(pid).unwrap_or(""));
+        if r == utils::empty_string() || has_reservations(&Product::from_db(String::from(pid), r)) {
+            return false;
+        }
+        db.del::<>(&pid.as_str()).is_ok()
+    });
+    if !deleted {
+        error!("product {} has reserved stock and can't be deleted", pid);
+        return false;
+    }
+    info!("delete product {}", pid);
+    search::unindex(&f_product);
+    for hash in &f_product.images {
+        media::release(pid, hash);
+    }
+    db::Interface::remove_from_list(&s.env, &s.handle, &get_list_key(&f_product.v_id), pid);
+    true
+}
+
+/// Upload an image, only the owning vendor may do this
+pub fn add_image(pid: &String, address: &String, data: &[u8]) -> Product {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} image can't be added by {}", pid, address);
+        return Default::default();
+    }
+    let processed = match media::process(data) {
+        Some(p) => p,
+        None => return Default::default(),
+    };
+    let hash = media::get_hash(&processed);
+    // the file is only stored once the product references it
+    let u_product = update(pid, &[], |p| {
+        if p.images.len() >= media::MAX_IMAGES || p.images.contains(&hash) {
+            error!("product {} can't add image {}", pid, &hash);
+            return None;
+        }
+        let mut images = p.images.clone();
+        images.push(String::from(&hash));
+        Some(Product { images, ..p })
+    });
+    if u_product.pid == utils::empty_string() || media::store(pid, &hash, &processed) {
+        return u_product;
+    }
+    // the file couldn't be written, take the image off the product again
+    update(pid, &[], |p| {
+        let images: Vec<String> = p.images.iter().filter(|i| **i != hash).cloned().collect();
+        Some(Product { images, ..p })
+    });
+    Default::default()
+}
+
+/// Remove an image, only the owning vendor may do this
+pub fn remove_image(pid: &String, address: &String, hash: &String) -> Product {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} image can't be removed by {}", pid, address);
+        return Default::default();
+    }
+    if !f_product.images.contains(hash) {
+        return Default::default();
+    }
+    let u_product = update(pid, &[], |p| {
+        let images: Vec<String> = p.images.iter().filter(|i| *i != hash).cloned().collect();
+        Some(Product { images, ..p })
+    });
+    // the file goes once no product references it
+    if u_product.pid != utils::empty_string() {
+        media::release(pid, hash);
+    }
+    u_product
+}
+
+/// Variant names and amounts must be usable for ordering
+fn is_valid_variant(v: &Variant) -> bool {
+    !v.name.trim().is_empty() && v.price >= 0 && v.qty >= v.reserved
+}
+
+/// Apply the fields present in the request to the variant
+fn apply_variant(v: Variant, r: &reqres::VariantRequest) -> Variant {
+    Variant {
+        name: r.name.clone().unwrap_or(v.name),
+        price: r.price.unwrap_or(v.price),
+        qty: r.qty.unwrap_or(v.qty),
+        ..v
+    }
+}
+
+/// Lookup a variant on the product, empty vrid or unknown returns default
+pub fn get_variant(p: &Product, vrid: &String) -> Variant {
+    p.variants.iter().find(|v| &v.vrid == vrid).cloned().unwrap_or_default()
+}
+
+/// Unit price for the chosen variant in the product currency,
+/// the base price is used without variants
+pub fn get_price(p: &Product, vrid: &String) -> i64 {
+    if vrid == &utils::empty_string() {
+        return p.p_price;
+    }
+    get_variant(p, vrid).price
+}
+
+/// Add a variant with its own price and stock, only the owning vendor may do this
+pub fn add_variant(pid: &String, address: &String, r: &reqres::VariantRequest) -> Product {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} variant can't be added by {}", pid, address);
+        return Default::default();
+    }
+    let skeleton = Variant {
+        vrid: format!("var{}", utils::generate_rnd()),
+        price: f_product.p_price,
+        ..Default::default()
+    };
+    let new_variant = apply_variant(skeleton, r);
+    if !is_valid_variant(&new_variant) {
+        error!("invalid variant for product {}", pid);
+        return Default::default();
+    }
+    debug!("insert variant: {:?}", &new_variant);
+    update(pid, &[], |p| {
+        let mut variants = p.variants.clone();
+        variants.push(new_variant);
+        Some(Product { variants, ..p })
+    })
+}
+
+/// Update a variant, only the owning vendor may do this
+pub fn modify_variant(
+    pid: &String,
+    address: &String,
+    vrid: &String,
+    r: &reqres::VariantRequest,
+) -> Product {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} variant can't be modified by {}", pid, address);
+        return Default::default();
+    }
+    info!("modify variant {} on product {}", vrid, pid);
+    update(pid, &[], |p| {
+        let f_variant: Variant = get_variant(&p, vrid);
+        if f_variant.vrid == utils::empty_string() {
+            return None;
+        }
+        let u_variant = apply_variant(f_variant, r);
+        if !is_valid_variant(&u_variant) {
+            error!("invalid variant for product {}", pid);
+            return None;
+        }
+        let variants: Vec<Variant> = p
+            .variants
+            .iter()
+            .map(|v| if &v.vrid == vrid { u_variant.clone() } else { v.clone() })
+            .collect();
+        Some(Product { variants, ..p })
+    })
+}
+
+/// Remove a variant, only the owning vendor may do this
+pub fn remove_variant(pid: &String, address: &String, vrid: &String) -> Product {
+    let f_product: Product = find(pid);
+    if f_product.pid == utils::empty_string() || !is_owner(&f_product, address) {
+        error!("product {} variant can't be removed by {}", pid, address);
+        return Default::default();
+    }
+    info!("remove variant {} from product {}", vrid, pid);
+    update(pid, &[], |p| {
+        // orders holding this variant must settle first
+        let f_variant: Variant = get_variant(&p, vrid);
+        if f_variant.vrid == utils::empty_string() || f_variant.reserved > 0 {
+            return None;
+        }
+        let variants: Vec<Variant> = p.variants.iter().filter(|v| &v.vrid != vrid).cloned().collect();
+        Some(Product { variants, ..p })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_price_test() {
+        let p = Product {
+            p_price: 100,
+            variants: vec![Variant {
+                vrid: String::from("var1"),
+                name: String::from("large"),
+                price: 150,
+                qty: 2,
+                reserved: 0,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(get_price(&p, &utils::empty_string()), 100);
+        assert_eq!(get_price(&p, &String::from("var1")), 150);
+        assert_eq!(get_variant(&p, &String::from("var2")), Default::default());
+    }
+
+    #[test]
+    fn is_valid_test() {
+        let p = Product { p_price: 100, qty: 3, reserved: 2, ..Default::default() };
+        assert!(is_valid(&p));
+        assert!(is_valid(&Product { p_price: 0, qty: 0, reserved: 0, ..p.clone() }));
+        assert!(!is_valid(&Product { p_price: -1, ..p.clone() }));
+        assert!(!is_valid(&Product { qty: -1, reserved: 0, ..p.clone() }));
+        assert!(!is_valid(&Product { qty: 1, ..p.clone() }));
+    }
+
+    #[test]
+    fn has_reservations_test() {
+        let variant = Variant { vrid: String::from("var1"), qty: 2, ..Default::default() };
+        let p = Product { qty: 3, variants: vec![variant.clone()], ..Default::default() };
+        assert!(!has_reservations(&p));
+        assert!(has_reservations(&Product { reserved: 1, ..p.clone() }));
+        let reserved = Variant { reserved: 1, ..variant };
+        assert!(has_reservations(&Product { variants: vec![reserved], ..p }));
+    }
+
+    #[test]
+    fn apply_stock_test() {
+        let none = utils::empty_string();
+        let p = Product { qty: 3, ..Default::default() };
+        let p = apply_stock(p, &none, 2, StockChange::Reserve).unwrap();
+        assert_eq!((p.qty, p.reserved, p.in_stock), (3, 2, true));
+        // only one unit left to reserve
+        assert!(apply_stock(p.clone(), &none, 2, StockChange::Reserve).is_none());
+        let p = apply_stock(p, &none, 1, StockChange::Release).unwrap();
+        assert_eq!((p.qty, p.reserved), (3, 1));
+        let p = apply_stock(p, &none, 1, StockChange::Commit).unwrap();
+        assert_eq!((p.qty, p.reserved), (2, 0));
+        assert!(apply_stock(p.clone(), &none, 1, StockChange::Commit).is_none());
+        let p = apply_stock(p, &none, 2, StockChange::Reserve).unwrap();
+        assert!(!p.in_stock);
+        let p = apply_stock(p, &none, 1, StockChange::Restock).unwrap();
+        assert_eq!((p.qty, p.reserved, p.in_stock), (3, 2, true));
+    }
+
+    #[test]
+    fn apply_stock_variant_test() {
+        let vrid = String::from("var1");
+        let p = Product {
+            qty: 10,
+            variants: vec![Variant { vrid: String::from(&vrid), qty: 1, ..Default::default() }],
+            ..Default::default()
+        };
+        let p = apply_stock(p, &vrid, 1, StockChange::Reserve).unwrap();
+        assert_eq!(get_variant(&p, &vrid).reserved, 1);
+        // product level stock is ignored once variants exist
+        assert!(!p.in_stock);
+        assert!(apply_stock(p, &String::from("var2"), 1, StockChange::Reserve).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+// Fiat exchange rates for pricing products in a currency other than xmr
+use crate::args;
+use async_trait::async_trait;
+use clap::Parser;
+use log::{debug, error, info};
+use rocket::serde::json::serde_json;
+use std::collections::HashMap;
+use std::fs;
+
+/// Piconero in one xmr
+pub const PICONERO: i64 = 1_000_000_000_000;
+
+/// Source of fiat rates. Rates are integer cents of the currency per xmr,
+/// keyed by uppercase currency code, e.g. {"USD": 15025}.
+#[async_trait]
+pub trait RateProvider: Send + Sync {
+    async fn get_rates(&self) -> HashMap<String, i64>;
+
+    /// Rate for a single currency, zero if it is unavailable
+    async fn get_rate(&self, currency: &str) -> i64 {
+        let rates = self.get_rates().await;
+        rates.get(&normalize(currency)).copied().filter(|r| *r > 0).unwrap_or(0)
+    }
+}
+
+/// Rates read from a local file so pricing works offline
+pub struct FileRateProvider {
+    pub path: String,
+}
+
+#[async_trait]
+impl RateProvider for FileRateProvider {
+    async fn get_rates(&self) -> HashMap<String, i64> {
+        debug!("reading rates from {}", &self.path);
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("failed to read rate file {}: {}", &self.path, e);
+                return HashMap::new();
+            }
+        };
+        parse(&contents)
+    }
+}
+
+/// Rates fetched from an HTTP endpoint through the i2p proxy
+pub struct HttpRateProvider {
+    pub url: String,
+    pub proxy: String,
+}
+
+#[async_trait]
+impl RateProvider for HttpRateProvider {
+    async fn get_rates(&self) -> HashMap<String, i64> {
+        debug!("fetching rates from {}", &self.url);
+        let client = reqwest::Proxy::all(&self.proxy)
+            .and_then(|proxy| reqwest::Client::builder().proxy(proxy).build());
+        let client = match client {
+            Ok(c) => c,
+            Err(e) => {
+                error!("failed to build rate client: {}", e);
+                return HashMap::new();
+            }
+        };
+        match client.get(&self.url).send().await {
+            Ok(response) => parse(&response.text().await.unwrap_or_default()),
+            Err(e) => {
+                error!("failed to fetch rates: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+}
+
+/// The http provider is used when a rate url is configured, otherwise the rate file
+pub fn get_provider() -> Box<dyn RateProvider> {
+    let args = args::Args::parse();
+    if args.rate_url.is_empty() {
+        Box::new(FileRateProvider { path: args.rate_file })
+    } else {
+        Box::new(HttpRateProvider { url: args.rate_url, proxy: args.i2p_proxy })
+    }
+}
+
+/// Currency codes are compared in uppercase, empty means xmr
+pub fn normalize(currency: &str) -> String {
+    currency.trim().to_uppercase()
+}
+
+/// Three letter codes (USD, EUR, ...) are accepted
+pub fn is_valid_currency(currency: &str) -> bool {
+    currency.len() == 3 && currency.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn parse(contents: &str) -> HashMap<String, i64> {
+    match serde_json::from_str::<HashMap<String, i64>>(contents) {
+        Ok(rates) => rates.into_iter().map(|(k, v)| (normalize(&k), v)).collect(),
+        Err(e) => {
+            error!("failed to parse rates: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Convert cents of a currency to piconero at `rate` cents per xmr, None
+/// if the result doesn't fit
+pub fn to_piconero(cents: i64, rate: i64) -> Option<i64> {
+    if rate <= 0 {
+        return Some(0);
+    }
+    i64::try_from(cents as i128 * PICONERO as i128 / rate as i128).ok()
+}
+
+/// Current rate for the currency from the configured provider, zero if unavailable
+pub async fn lock_rate(currency: &str) -> i64 {
+    let rate = get_provider().get_rate(currency).await;
+    info!("locked {} rate at {}", normalize(currency), rate);
+    rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_piconero_test() {
+        // 150.00 at 150.00 per xmr is one xmr
+        assert_eq!(to_piconero(15000, 15000), Some(PICONERO));
+        assert_eq!(to_piconero(7500, 15000), Some(PICONERO / 2));
+        assert_eq!(to_piconero(100, 0), Some(0));
+        assert_eq!(to_piconero(i64::MAX, 1), None);
+    }
+
+    #[rocket::async_test]
+    async fn file_rate_provider_test() {
+        let path = std::env::temp_dir().join("mpi2p-rates-test.json");
+        fs::write(&path, r#"{"usd": 15025, "EUR": 14000}"#).unwrap();
+        let provider = FileRateProvider { path: String::from(path.to_str().unwrap()) };
+        assert_eq!(provider.get_rate("USD").await, 15025);
+        assert_eq!(provider.get_rate("eur").await, 14000);
+        assert_eq!(provider.get_rate("GBP").await, 0);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{models, utils};
+use crate::{evidence, models, utils, vendor};
 use serde::{Deserialize, Serialize};
 // All http requests and responses are here
 
@@ -21,12 +21,13 @@ pub struct XmrRpcWalletParams {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct XmrRpcMakeParams {
     pub multisig_info: Vec<String>,
-    pub threshold: u8,
+    pub threshold: u32,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcFinalizeParams {
+pub struct XmrRpcExchangeKeysParams {
     pub multisig_info: Vec<String>,
+    pub password: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -39,75 +40,144 @@ pub struct XmrRpcSignMultisigParams {
     pub tx_data_hex: String,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcSubmitMultisigParams {
+    pub tx_data_hex: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Destination {
+    pub address: String,
+    pub amount: u128,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcTransferParams {
+    pub destinations: Vec<Destination>,
+    pub account_index: u32,
+    /// output indices that pay the fee
+    pub subtract_fee_from_outputs: Vec<u32>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcDescribeTransferParams {
+    pub multisig_txset: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcGetTransfersParams {
+    #[serde(rename = "in")]
+    pub incoming: bool,
+    pub pool: bool,
+    pub account_index: u32,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct XmrRpcBalanceParams {
-    pub account_index: u8,
-    pub address_indices: Vec<u8>,
+    pub account_index: u32,
+    pub address_indices: Vec<u32>,
     pub all_accounts: bool,
     pub strict: bool,
 }
-// requests
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcCreateAddressParams {
+    pub account_index: u32,
+    pub label: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcSweepAllParams {
+    pub address: String,
+    pub account_index: u32,
+    pub subaddr_indices: Vec<u32>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcGetAddressParams {
+    pub account_index: u32,
+    pub address_index: Vec<u32>,
+}
+
+/// Account (major) and subaddress (minor) index
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SubaddressIndex {
+    pub major: u32,
+    pub minor: u32,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcCreateRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcWalletParams,
+pub struct XmrRpcLabelAddressParams {
+    pub index: SubaddressIndex,
+    pub label: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
+pub struct XmrRpcGetTransferByTxidParams {
+    pub txid: String,
+    pub account_index: u32,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcBalanceRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcBalanceParams,
+pub struct XmrRpcIncomingTransfersParams {
+    /// all, available or unavailable
+    pub transfer_type: String,
+    pub account_index: u32,
+    pub subaddr_indices: Vec<u32>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcMakeRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcMakeParams,
+pub struct XmrRpcTransferSplitParams {
+    pub destinations: Vec<Destination>,
+    pub account_index: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct XmrRpcExportKeyImagesParams {
+    /// false exports only images not exported before
+    pub all: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SignedKeyImage {
+    pub key_image: String,
+    pub signature: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcFinalizeRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcFinalizeParams,
+pub struct XmrRpcImportKeyImagesParams {
+    pub signed_key_images: Vec<SignedKeyImage>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcImportRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcImportParams,
+pub struct XmrRpcRefreshParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_height: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcSignMultisigRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcSignMultisigParams,
+pub struct XmrRpcValidateAddressParams {
+    pub address: String,
+    /// accept mainnet, stagenet and testnet addresses
+    pub any_net_type: bool,
+    pub allow_openalias: bool,
+}
+
+/// Payment request fields in a monero: uri
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct PaymentUri {
+    pub address: String,
+    #[serde(default)]
+    pub amount: u128,
+    #[serde(default)]
+    pub recipient_name: String,
+    #[serde(default)]
+    pub tx_description: String,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct XmrRpcVerifyRequest {
-    pub jsonrpc: String,
-    pub id: String,
-    pub method: String,
-    pub params: XmrRpcVerifyParams,
+pub struct XmrRpcParseUriParams {
+    pub uri: String,
 }
 // results
 #[derive(Deserialize, Debug)]
@@ -121,8 +191,18 @@ pub struct XmrRpcVersionResult {
 }
 
 #[derive(Deserialize, Debug)]
-pub struct XmrRpcFinalizeResult {
+pub struct XmrRpcExchangeKeysResult {
     pub address: String,
+    /// empty once the last round is done
+    pub multisig_info: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcIsMultisigResult {
+    pub multisig: bool,
+    pub ready: bool,
+    pub threshold: u32,
+    pub total: u32,
 }
 
 #[derive(Deserialize, Debug)]
@@ -148,13 +228,194 @@ pub struct XmrRpcImportResult {
 
 #[derive(Deserialize, Debug)]
 pub struct XmrRpcSignMultisigResult {
+    /// txset with this wallet's signature added
+    pub tx_data_hex: String,
+    pub tx_hash_list: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcSubmitMultisigResult {
+    pub tx_hash_list: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcTransferResult {
+    pub amount: u128,
+    pub fee: u128,
+    /// txset for the other signers, signed by this wallet
+    pub multisig_txset: String,
+    pub tx_hash: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TransferDescription {
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub recipients: Vec<Destination>,
+    pub change_address: String,
+    pub change_amount: u128,
+    pub fee: u128,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcDescribeTransferResult {
+    pub desc: Vec<TransferDescription>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TransferEntry {
+    #[serde(default)]
+    pub address: String,
+    pub amount: u128,
+    /// zero for transfers still in the pool
+    #[serde(default)]
+    pub confirmations: u64,
+    #[serde(default)]
+    pub fee: u128,
+    pub height: u64,
+    #[serde(default)]
+    pub subaddr_index: SubaddressIndex,
+    #[serde(default)]
+    pub timestamp: u64,
+    pub txid: String,
+    /// in, out, pending, failed or pool
+    #[serde(default, rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcGetTransfersResult {
+    #[serde(default, rename = "in")]
+    pub incoming: Vec<TransferEntry>,
+    #[serde(default)]
+    pub pool: Vec<TransferEntry>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AddressInfo {
+    pub address: String,
+    pub address_index: u32,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub used: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcGetAddressResult {
+    /// primary address of the account
+    pub address: String,
+    #[serde(default)]
+    pub addresses: Vec<AddressInfo>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcGetTransferByTxidResult {
+    pub transfer: TransferEntry,
+    /// one entry per destination when a tx pays several subaddresses
+    #[serde(default)]
+    pub transfers: Vec<TransferEntry>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct IncomingTransfer {
+    pub amount: u128,
+    #[serde(default)]
+    pub key_image: String,
+    pub spent: bool,
+    pub subaddr_index: SubaddressIndex,
+    pub tx_hash: String,
+    #[serde(default)]
+    pub unlocked: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcIncomingTransfersResult {
+    /// missing when the wallet has no matching outputs
+    #[serde(default)]
+    pub transfers: Vec<IncomingTransfer>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcTransferSplitResult {
+    #[serde(default)]
+    pub amount_list: Vec<u128>,
+    #[serde(default)]
+    pub fee_list: Vec<u128>,
+    /// txset for the other signers in a multisig wallet
+    #[serde(default)]
+    pub multisig_txset: String,
+    #[serde(default)]
+    pub tx_hash_list: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcExportKeyImagesResult {
+    #[serde(default)]
+    pub offset: u32,
+    #[serde(default)]
+    pub signed_key_images: Vec<SignedKeyImage>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcImportKeyImagesResult {
+    pub height: u64,
+    pub spent: u128,
+    pub unspent: u128,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcRefreshResult {
+    pub blocks_fetched: u64,
+    pub received_money: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcGetHeightResult {
+    pub height: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcValidateAddressResult {
+    pub valid: bool,
+    #[serde(default)]
+    pub integrated: bool,
+    #[serde(default)]
+    pub subaddress: bool,
+    /// mainnet, stagenet or testnet
+    #[serde(default)]
+    pub nettype: String,
+    #[serde(default)]
+    pub openalias_address: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcMakeUriResult {
+    pub uri: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcParseUriResult {
+    pub uri: PaymentUri,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcCreateAddressResult {
+    pub address: String,
+    pub address_index: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcSweepAllResult {
+    pub amount_list: Vec<u128>,
+    pub fee_list: Vec<u128>,
     pub tx_hash_list: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct SubAddressInfo {
-    pub account_index: u8,
-    pub address_index: u8,
+    pub account_index: u32,
+    pub address_index: u32,
     pub address: String,
     pub balance: u128,
     pub unlocked_balance: u128,
@@ -248,16 +509,36 @@ impl Default for XmrRpcMakeResponse {
 }
 
 #[derive(Deserialize, Debug)]
-pub struct XmrRpcFinalizeResponse {
-    pub result: XmrRpcFinalizeResult,
+pub struct XmrRpcExchangeKeysResponse {
+    pub result: XmrRpcExchangeKeysResult,
 }
 
-impl Default for XmrRpcFinalizeResponse {
+impl Default for XmrRpcExchangeKeysResponse {
     fn default() -> Self {
-        XmrRpcFinalizeResponse {
+        XmrRpcExchangeKeysResponse {
             result:
-            XmrRpcFinalizeResult {
+            XmrRpcExchangeKeysResult {
                 address: utils::empty_string(),
+                multisig_info: utils::empty_string(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcIsMultisigResponse {
+    pub result: XmrRpcIsMultisigResult,
+}
+
+impl Default for XmrRpcIsMultisigResponse {
+    fn default() -> Self {
+        XmrRpcIsMultisigResponse {
+            result:
+            XmrRpcIsMultisigResult {
+                multisig: false,
+                ready: false,
+                threshold: 0,
+                total: 0,
             }
         }
     }
@@ -305,11 +586,169 @@ impl Default for XmrRpcSignMultisigResponse {
         XmrRpcSignMultisigResponse {
             result:
             XmrRpcSignMultisigResult {
+                tx_data_hex: utils::empty_string(),
                 tx_hash_list:Vec::new(),
             }
         }
     }
 }
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcSubmitMultisigResponse {
+    pub result: XmrRpcSubmitMultisigResult,
+}
+
+impl Default for XmrRpcSubmitMultisigResponse {
+    fn default() -> Self {
+        XmrRpcSubmitMultisigResponse {
+            result:
+            XmrRpcSubmitMultisigResult {
+                tx_hash_list: Vec::new(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcTransferResponse {
+    pub result: XmrRpcTransferResult,
+}
+
+impl Default for XmrRpcTransferResponse {
+    fn default() -> Self {
+        XmrRpcTransferResponse {
+            result:
+            XmrRpcTransferResult {
+                amount: 0,
+                fee: 0,
+                multisig_txset: utils::empty_string(),
+                tx_hash: utils::empty_string(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcDescribeTransferResponse {
+    pub result: XmrRpcDescribeTransferResult,
+}
+
+impl Default for XmrRpcDescribeTransferResponse {
+    fn default() -> Self {
+        XmrRpcDescribeTransferResponse {
+            result:
+            XmrRpcDescribeTransferResult {
+                desc: Vec::new(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcGetTransfersResponse {
+    pub result: XmrRpcGetTransfersResult,
+}
+
+impl Default for XmrRpcGetTransfersResponse {
+    fn default() -> Self {
+        XmrRpcGetTransfersResponse {
+            result:
+            XmrRpcGetTransfersResult {
+                incoming: Vec::new(),
+                pool: Vec::new(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcCreateAddressResponse {
+    pub result: XmrRpcCreateAddressResult,
+}
+
+impl Default for XmrRpcCreateAddressResponse {
+    fn default() -> Self {
+        XmrRpcCreateAddressResponse {
+            result:
+            XmrRpcCreateAddressResult {
+                address: utils::empty_string(),
+                address_index: 0,
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct XmrRpcSweepAllResponse {
+    pub result: XmrRpcSweepAllResult,
+}
+
+impl Default for XmrRpcSweepAllResponse {
+    fn default() -> Self {
+        XmrRpcSweepAllResponse {
+            result:
+            XmrRpcSweepAllResult {
+                amount_list: Vec::new(),
+                fee_list: Vec::new(),
+                tx_hash_list: Vec::new(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcGetAddressResponse {
+    pub result: XmrRpcGetAddressResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcGetTransferByTxidResponse {
+    pub result: XmrRpcGetTransferByTxidResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcIncomingTransfersResponse {
+    pub result: XmrRpcIncomingTransfersResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcTransferSplitResponse {
+    pub result: XmrRpcTransferSplitResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcExportKeyImagesResponse {
+    pub result: XmrRpcExportKeyImagesResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcImportKeyImagesResponse {
+    pub result: XmrRpcImportKeyImagesResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcRefreshResponse {
+    pub result: XmrRpcRefreshResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcGetHeightResponse {
+    pub result: XmrRpcGetHeightResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcValidateAddressResponse {
+    pub result: XmrRpcValidateAddressResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcMakeUriResponse {
+    pub result: XmrRpcMakeUriResult,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct XmrRpcParseUriResponse {
+    pub result: XmrRpcParseUriResult,
+}
 // END XMR Structs
 
 #[derive(Serialize, Deserialize)]
@@ -352,30 +791,223 @@ impl Default for GetVendorResponse {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetVendorProfileResponse {
+    pub active: bool,
+    pub address: String,
+    pub description: String,
+    pub name: String,
+    pub pgp: String,
+    pub account_age: i64,
+    pub avg_ship_time: i64,
+    pub completed_orders: u64,
+    pub dispute_rate: f64,
+    pub feedback_count: u64,
+    pub feedback_score: f64,
+    pub total_orders: u64,
+}
+
+impl Default for GetVendorProfileResponse {
+    fn default() -> Self {
+        GetVendorProfileResponse {
+            active: false,
+            address: utils::empty_string(),
+            description: utils::empty_string(),
+            name: utils::empty_string(),
+            pgp: utils::empty_string(),
+            account_age: 0,
+            avg_ship_time: 0,
+            completed_orders: 0,
+            dispute_rate: 0.0,
+            feedback_count: 0,
+            feedback_score: 0.0,
+            total_orders: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FeedbackRequest {
+    pub rating: u8,
+    pub comment: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FeedbackReplyRequest {
+    pub reply: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetFeedbackResponse {
+    pub orid: String,
+    pub pid: String,
+    pub rating: u8,
+    pub comment: String,
+    pub created: i64,
+    pub reply: String,
+    pub reply_date: i64,
+}
+
+impl Default for GetFeedbackResponse {
+    fn default() -> Self {
+        GetFeedbackResponse {
+            orid: utils::empty_string(),
+            pid: utils::empty_string(),
+            rating: 0,
+            comment: utils::empty_string(),
+            created: 0,
+            reply: utils::empty_string(),
+            reply_date: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetFeedbackListResponse {
+    pub feedback: Vec<GetFeedbackResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetBondResponse {
+    pub address: String,
+    pub amount: u128,
+    pub created: i64,
+    pub status: String,
+    pub tx_hash: String,
+}
+
+impl Default for GetBondResponse {
+    fn default() -> Self {
+        GetBondResponse {
+            address: utils::empty_string(),
+            amount: 0,
+            created: 0,
+            status: utils::empty_string(),
+            tx_hash: utils::empty_string(),
+        }
+    }
+}
+
+/// Editable product fields, absent fields are left unchanged
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ProductRequest {
+    pub description: Option<String>,
+    pub name: Option<String>,
+    pub price: Option<i64>,
+    pub qty: Option<i64>,
+    pub category: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub currency: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct VariantRequest {
+    pub name: Option<String>,
+    pub price: Option<i64>,
+    pub qty: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ShippingRequest {
+    pub name: Option<String>,
+    pub price: Option<i64>,
+    pub regions: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetVariantResponse {
+    pub vrid: String,
+    pub name: String,
+    pub price: i64,
+    pub qty: i64,
+    pub reserved: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetShippingResponse {
+    pub sid: String,
+    pub vid: String,
+    pub name: String,
+    pub price: i64,
+    pub regions: Vec<String>,
+}
+
+impl Default for GetShippingResponse {
+    fn default() -> Self {
+        GetShippingResponse {
+            sid: utils::empty_string(),
+            vid: utils::empty_string(),
+            name: utils::empty_string(),
+            price: 0,
+            regions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetShippingListResponse {
+    pub shipping: Vec<GetShippingResponse>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct GetProductResponse {
     pub pid: String,
+    pub vid: String,
     pub in_stock: bool,
     pub description: String,
     pub name: String,
     pub price: i64,
+    pub currency: String,
     pub qty: i64,
+    pub reserved: i64,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub images: Vec<String>,
+    pub variants: Vec<GetVariantResponse>,
 }
 
 impl Default for GetProductResponse {
     fn default() -> Self {
         GetProductResponse {
             pid: utils::empty_string(),
+            vid: utils::empty_string(),
             in_stock: false,
             description: utils::empty_string(),
             name: utils::empty_string(),
             price: 0,
+            currency: utils::empty_string(),
             qty: 0,
+            reserved: 0,
+            category: utils::empty_string(),
+            tags: Vec::new(),
+            images: Vec::new(),
+            variants: Vec::new(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetSearchResponse {
+    pub products: Vec<GetProductResponse>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct GetVendorProductsResponse {
@@ -417,21 +1049,199 @@ impl Default for GetAuthResponse {
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct GetDisputeResponse {
+    pub did: String,
     pub orid: String,
     pub created: i64,
-    pub tx_set: String,
+    pub opened_by: String,
+    pub reason: String,
+    pub mediator: String,
+    /// armored key evidence is encrypted to, only on single dispute lookups
+    pub mediator_pgp: String,
+    pub status: String,
+    pub resolution: String,
+    pub resolved: i64,
+    /// vendor's percentage of a split, the customer gets the rest
+    pub split: u8,
 }
 
 impl Default for GetDisputeResponse {
     fn default() -> Self {
         GetDisputeResponse {
+            did: utils::empty_string(),
             orid: utils::empty_string(),
             created: 0,
-            tx_set: utils::empty_string(),
+            opened_by: utils::empty_string(),
+            reason: utils::empty_string(),
+            mediator: utils::empty_string(),
+            mediator_pgp: utils::empty_string(),
+            status: utils::empty_string(),
+            resolution: utils::empty_string(),
+            resolved: 0,
+            split: 0,
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetDisputesResponse {
+    pub disputes: Vec<GetDisputeResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DisputeRequest {
+    pub reason: String,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CommissionRequest {
+    /// percent or flat
+    pub kind: String,
+    /// basis points for percent, piconero for flat
+    pub value: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetCommissionResponse {
+    /// empty for the default commission
+    pub category: String,
+    pub kind: String,
+    pub value: i64,
+}
+
+impl Default for GetCommissionResponse {
+    fn default() -> Self {
+        GetCommissionResponse {
+            category: utils::empty_string(),
+            kind: utils::empty_string(),
+            value: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetCommissionsResponse {
+    pub commissions: Vec<GetCommissionResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetCommissionReportResponse {
+    pub from: i64,
+    pub to: i64,
+    /// orders whose release paid a commission in the period
+    pub orders: Vec<String>,
+    /// piconero collected
+    pub total: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct StatementRequest {
+    /// ascii armored pgp message
+    pub statement: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetEvidenceResponse {
+    pub eid: String,
+    pub orid: String,
+    pub role: String,
+    pub kind: String,
+    pub created: i64,
+    pub data: String,
+}
+
+impl Default for GetEvidenceResponse {
+    fn default() -> Self {
+        GetEvidenceResponse {
+            eid: utils::empty_string(),
+            orid: utils::empty_string(),
+            role: utils::empty_string(),
+            kind: utils::empty_string(),
+            created: 0,
+            data: utils::empty_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TimelineEventResponse {
+    pub timestamp: i64,
+    pub kind: String,
+    pub role: String,
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetTimelineResponse {
+    pub orid: String,
+    pub events: Vec<TimelineEventResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MediatorRequest {
+    pub xmr_address: String,
+    pub name: String,
+    pub pgp: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetMediatorResponse {
+    pub mid: String,
+    pub xmr_address: String,
+    pub name: String,
+    pub pgp: String,
+    pub active: bool,
+    pub created: i64,
+}
+
+impl Default for GetMediatorResponse {
+    fn default() -> Self {
+        GetMediatorResponse {
+            mid: utils::empty_string(),
+            xmr_address: utils::empty_string(),
+            name: utils::empty_string(),
+            pgp: utils::empty_string(),
+            active: false,
+            created: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GetMediatorsResponse {
+    pub mediators: Vec<GetMediatorResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OrderRequest {
+    pub quantity: i64,
+    /// required when the product has variants
+    pub variant: Option<String>,
+    pub shipping: String,
+    /// destination region checked against the shipping option
+    pub region: String,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OrderUpdateRequest {
+    pub update_type: i32,
+    pub data: String,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct GetOrderResponse {
@@ -439,25 +1249,38 @@ pub struct GetOrderResponse {
     pub pid: String,
     pub xmr_address: String,
     pub cust_msig_info: String,
-    pub cust_kex_1: String,
-    pub cust_kex_2: String,
-    pub cust_kex_3: String,
+    pub cust_kex: Vec<String>,
+    pub cust_msig_export: String,
     pub date: i64,
     pub deliver_date: i64,
+    pub funding: String,
+    pub received: i64,
     pub ship_date: i64,
     pub hash: String,
     pub msig_prepare: String,
     pub msig_make: String,
-    pub msig_kex_1: String,
-    pub msig_kex_2: String,
-    pub msig_kex_3: String,
+    pub msig_kex: Vec<String>,
+    pub msig_export: String,
+    pub msig_txset: String,
+    pub signers: Vec<String>,
+    pub payout: String,
+    pub payout_hash: String,
+    pub refund_address: String,
     pub subaddress: String,
     pub status: String,
     pub quantity: i64,
-    pub vend_kex_1: String,
-    pub vend_kex_2: String,
-    pub vend_kex_3: String,
+    pub vend_kex: Vec<String>,
+    pub vend_msig_export: String,
     pub vend_msig_info: String,
+    pub variant: String,
+    pub shipping: String,
+    pub amount: i64,
+    /// market commission paid out of the release
+    pub fee: i64,
+    pub fee_address: String,
+    pub cancel: Vec<String>,
+    pub currency: String,
+    pub rate: i64,
 }
 
 impl Default for GetOrderResponse {
@@ -467,25 +1290,37 @@ impl Default for GetOrderResponse {
             pid: utils::empty_string(),
             xmr_address: utils::empty_string(),
             cust_msig_info: utils::empty_string(),
-            cust_kex_1: utils::empty_string(),
-            cust_kex_2: utils::empty_string(),
-            cust_kex_3: utils::empty_string(),
+            cust_kex: Vec::new(),
+            cust_msig_export: utils::empty_string(),
             date: 0,
             deliver_date: 0,
+            funding: utils::empty_string(),
+            received: 0,
             ship_date: 0,
             hash: utils::empty_string(),
             msig_prepare: utils::empty_string(),
             msig_make: utils::empty_string(),
-            msig_kex_1: utils::empty_string(),
-            msig_kex_2: utils::empty_string(),
-            msig_kex_3: utils::empty_string(),
+            msig_kex: Vec::new(),
+            msig_export: utils::empty_string(),
+            msig_txset: utils::empty_string(),
+            signers: Vec::new(),
+            payout: utils::empty_string(),
+            payout_hash: utils::empty_string(),
+            refund_address: utils::empty_string(),
             subaddress: utils::empty_string(),
             status: utils::empty_string(),
             quantity: 0,
-            vend_kex_1: utils::empty_string(),
-            vend_kex_2: utils::empty_string(),
-            vend_kex_3: utils::empty_string(),
+            vend_kex: Vec::new(),
+            vend_msig_export: utils::empty_string(),
             vend_msig_info: utils::empty_string(),
+            variant: utils::empty_string(),
+            shipping: utils::empty_string(),
+            amount: 0,
+            fee: 0,
+            fee_address: utils::empty_string(),
+            cancel: Vec::new(),
+            currency: utils::empty_string(),
+            rate: 0,
         }
     }
 }
@@ -527,6 +1362,58 @@ impl GetVendorResponse {
     }
 }
 
+impl GetVendorProfileResponse {
+    pub fn build(m_vendor: models::Vendor, stats: vendor::VendorStats) -> Self {
+        GetVendorProfileResponse {
+            active: m_vendor.active,
+            address: m_vendor.v_xmr_address,
+            description: m_vendor.v_description,
+            name: m_vendor.v_name,
+            pgp: m_vendor.v_pgp,
+            account_age: stats.account_age,
+            avg_ship_time: stats.avg_ship_time,
+            completed_orders: stats.completed_orders,
+            dispute_rate: stats.dispute_rate,
+            feedback_count: stats.feedback_count,
+            feedback_score: stats.feedback_score,
+            total_orders: stats.total_orders,
+        }
+    }
+}
+
+impl GetFeedbackResponse {
+    pub fn build(m_feedback: models::Feedback) -> Self {
+        GetFeedbackResponse {
+            orid: m_feedback.orid,
+            pid: m_feedback.p_id,
+            rating: m_feedback.rating,
+            comment: m_feedback.comment,
+            created: m_feedback.created,
+            reply: m_feedback.reply,
+            reply_date: m_feedback.reply_date,
+        }
+    }
+}
+
+impl GetFeedbackListResponse {
+    pub fn build(m_feedback: Vec<models::Feedback>) -> Self {
+        let feedback = m_feedback.into_iter().map(GetFeedbackResponse::build).collect();
+        GetFeedbackListResponse { feedback }
+    }
+}
+
+impl GetBondResponse {
+    pub fn build(m_bond: models::Bond) -> Self {
+        GetBondResponse {
+            address: m_bond.address,
+            amount: m_bond.amount,
+            created: m_bond.created,
+            status: m_bond.status,
+            tx_hash: m_bond.tx_hash,
+        }
+    }
+}
+
 impl GetAuthResponse {
     pub fn build(m_auth: models::Authorization) -> Self {
         GetAuthResponse {
@@ -543,37 +1430,169 @@ impl GetAuthResponse {
 impl GetDisputeResponse {
     pub fn build(m_dispute: models::Dispute) -> Self {
         GetDisputeResponse {
+            did: m_dispute.did,
             orid: m_dispute.orid,
             created: m_dispute.created,
-            tx_set: m_dispute.tx_set,
+            opened_by: m_dispute.opened_by,
+            reason: m_dispute.reason,
+            mediator: m_dispute.m_id,
+            mediator_pgp: utils::empty_string(),
+            status: m_dispute.status,
+            resolution: m_dispute.resolution,
+            resolved: m_dispute.resolved,
+            split: m_dispute.split,
         }
     }
 }
 
+impl GetDisputesResponse {
+    pub fn build(m_disputes: Vec<models::Dispute>) -> Self {
+        let disputes = m_disputes.into_iter().map(GetDisputeResponse::build).collect();
+        GetDisputesResponse { disputes }
+    }
+}
+
+impl GetCommissionResponse {
+    pub fn build(m_commission: models::Commission) -> Self {
+        GetCommissionResponse {
+            category: m_commission.category,
+            kind: m_commission.kind,
+            value: m_commission.value,
+        }
+    }
+}
+
+impl GetCommissionsResponse {
+    pub fn build(m_commissions: Vec<models::Commission>) -> Self {
+        let commissions = m_commissions.into_iter().map(GetCommissionResponse::build).collect();
+        GetCommissionsResponse { commissions }
+    }
+}
+
+impl GetCommissionReportResponse {
+    pub fn build(from: i64, to: i64, m_orders: Vec<models::Order>, total: i64) -> Self {
+        let orders = m_orders.into_iter().map(|o| o.orid).collect();
+        GetCommissionReportResponse { from, to, orders, total }
+    }
+}
+
+impl GetEvidenceResponse {
+    pub fn build(m_evidence: models::Evidence) -> Self {
+        GetEvidenceResponse {
+            eid: m_evidence.eid,
+            orid: m_evidence.orid,
+            role: m_evidence.role,
+            kind: m_evidence.kind,
+            created: m_evidence.created,
+            data: m_evidence.data,
+        }
+    }
+}
+
+impl GetTimelineResponse {
+    pub fn build(orid: String, m_events: Vec<evidence::TimelineEvent>) -> Self {
+        let events = m_events
+            .into_iter()
+            .map(|e| TimelineEventResponse {
+                timestamp: e.timestamp,
+                kind: e.kind,
+                role: e.role,
+                detail: e.detail,
+            })
+            .collect();
+        GetTimelineResponse { orid, events }
+    }
+}
+
+impl GetMediatorResponse {
+    pub fn build(m_mediator: models::Mediator) -> Self {
+        GetMediatorResponse {
+            mid: m_mediator.mid,
+            xmr_address: m_mediator.m_xmr_address,
+            name: m_mediator.m_name,
+            pgp: m_mediator.m_pgp,
+            active: m_mediator.active,
+            created: m_mediator.created,
+        }
+    }
+}
+
+impl GetMediatorsResponse {
+    pub fn build(m_mediators: Vec<models::Mediator>) -> Self {
+        let mediators = m_mediators.into_iter().map(GetMediatorResponse::build).collect();
+        GetMediatorsResponse { mediators }
+    }
+}
+
 impl GetProductResponse {
     pub fn build(m_product: models::Product) -> Self {
         GetProductResponse {
             pid: m_product.pid,
+            vid: m_product.v_id,
             in_stock: m_product.in_stock,
             description: m_product.p_description,
             name: m_product.p_name,
             price: m_product.p_price,
+            currency: m_product.currency,
             qty: m_product.qty,
+            reserved: m_product.reserved,
+            category: m_product.category,
+            tags: m_product.tags,
+            images: m_product.images,
+            variants: m_product.variants.into_iter().map(GetVariantResponse::build).collect(),
         }
     }
 }
 
+impl GetVariantResponse {
+    pub fn build(m_variant: models::Variant) -> Self {
+        GetVariantResponse {
+            vrid: m_variant.vrid,
+            name: m_variant.name,
+            price: m_variant.price,
+            qty: m_variant.qty,
+            reserved: m_variant.reserved,
+        }
+    }
+}
+
+impl GetShippingResponse {
+    pub fn build(m_shipping: models::Shipping) -> Self {
+        GetShippingResponse {
+            sid: m_shipping.sid,
+            vid: m_shipping.v_id,
+            name: m_shipping.name,
+            price: m_shipping.price,
+            regions: m_shipping.regions,
+        }
+    }
+}
+
+impl GetShippingListResponse {
+    pub fn build(m_shipping: Vec<models::Shipping>) -> Self {
+        let shipping = m_shipping.into_iter().map(GetShippingResponse::build).collect();
+        GetShippingListResponse { shipping }
+    }
+}
+
 impl GetVendorProductsResponse {
     pub fn build(m_products: Vec<models::Product>) -> Self {
         let mut v_res: Vec<GetProductResponse> = Vec::new();
         for m in m_products {
             let p_res: GetProductResponse = GetProductResponse {
                 pid: m.pid,
+                vid: m.v_id,
                 in_stock: m.in_stock,
                 description: m.p_description,
                 name: m.p_name,
                 price: m.p_price,
+                currency: m.currency,
                 qty: m.qty,
+                reserved: m.reserved,
+                category: m.category,
+                tags: m.tags,
+                images: m.images,
+                variants: m.variants.into_iter().map(GetVariantResponse::build).collect(),
             };
             v_res.push(p_res);
         }
@@ -581,6 +1600,13 @@ impl GetVendorProductsResponse {
     }
 }
 
+impl GetSearchResponse {
+    pub fn build(m_products: Vec<models::Product>, page: usize, per_page: usize, total: usize) -> Self {
+        let products = m_products.into_iter().map(GetProductResponse::build).collect();
+        GetSearchResponse { products, page, per_page, total }
+    }
+}
+
 impl GetOrderResponse {
     pub fn build(pid: String, m_order: models::Order) -> Self {
         GetOrderResponse {
@@ -588,25 +1614,37 @@ impl GetOrderResponse {
             pid,
             xmr_address: m_order.o_xmr_address,
             cust_msig_info: m_order.o_cust_msig_info,
-            cust_kex_1: m_order.o_cust_kex_1,
-            cust_kex_2: m_order.o_cust_kex_2,
-            cust_kex_3: m_order.o_cust_kex_3,
+            cust_kex: m_order.o_cust_kex,
+            cust_msig_export: m_order.o_cust_msig_export,
             date: m_order.o_date,
             deliver_date: m_order.o_deliver_date,
+            funding: m_order.o_funding,
+            received: m_order.o_received,
             ship_date: m_order.o_ship_date,
             hash: m_order.o_hash,
             msig_prepare: m_order.o_msig_prepare,
             msig_make: m_order.o_msig_make,
-            msig_kex_1: m_order.o_msig_kex_1,
-            msig_kex_2: m_order.o_msig_kex_2,
-            msig_kex_3: m_order.o_msig_kex_3,
+            msig_kex: m_order.o_msig_kex,
+            msig_export: m_order.o_msig_export,
+            msig_txset: m_order.o_msig_txset,
+            signers: m_order.o_signers,
+            payout: m_order.o_payout,
+            payout_hash: m_order.o_payout_hash,
+            refund_address: m_order.o_refund_address,
             subaddress: m_order.o_subaddress,
             status: m_order.o_status,
             quantity: m_order.o_quantity,
-            vend_kex_1: m_order.o_vend_kex_1,
-            vend_kex_2: m_order.o_vend_kex_2,
-            vend_kex_3: m_order.o_vend_kex_3,
+            vend_kex: m_order.o_vend_kex,
+            vend_msig_export: m_order.o_vend_msig_export,
             vend_msig_info: m_order.o_vend_msig_info,
+            variant: m_order.o_variant,
+            shipping: m_order.o_shipping,
+            amount: m_order.o_amount,
+            fee: m_order.o_fee,
+            fee_address: m_order.o_fee_address,
+            cancel: m_order.o_cancel,
+            currency: m_order.o_currency,
+            rate: m_order.o_rate,
         }
     }
 }
@@ -620,25 +1658,37 @@ impl GetOrdersResponse {
                 pid: m.p_id,
                 xmr_address: m.o_xmr_address,
                 cust_msig_info: m.o_cust_msig_info,
-                cust_kex_1: m.o_cust_kex_1,
-                cust_kex_2: m.o_cust_kex_2,
-                cust_kex_3: m.o_cust_kex_3,
+                cust_kex: m.o_cust_kex,
+                cust_msig_export: m.o_cust_msig_export,
                 date: m.o_date,
                 deliver_date: m.o_deliver_date,
+                funding: m.o_funding,
+                received: m.o_received,
                 ship_date: m.o_ship_date,
                 hash: m.o_hash,
                 msig_prepare: m.o_msig_prepare,
                 msig_make: m.o_msig_make,
-                msig_kex_1: m.o_msig_kex_1,
-                msig_kex_2: m.o_msig_kex_2,
-                msig_kex_3: m.o_msig_kex_3,
+                msig_kex: m.o_msig_kex,
+                msig_export: m.o_msig_export,
+                msig_txset: m.o_msig_txset,
+                signers: m.o_signers,
+                payout: m.o_payout,
+                payout_hash: m.o_payout_hash,
+                refund_address: m.o_refund_address,
                 subaddress: m.o_subaddress,
                 status: m.o_status,
                 quantity: m.o_quantity,
-                vend_kex_1: m.o_vend_kex_1,
-                vend_kex_2: m.o_vend_kex_2,
-                vend_kex_3: m.o_vend_kex_3,
+                vend_kex: m.o_vend_kex,
+                vend_msig_export: m.o_vend_msig_export,
                 vend_msig_info: m.o_vend_msig_info,
+                variant: m.o_variant,
+                shipping: m.o_shipping,
+                amount: m.o_amount,
+                fee: m.o_fee,
+                fee_address: m.o_fee_address,
+                cancel: m.o_cancel,
+                currency: m.o_currency,
+                rate: m.o_rate,
             };
             v_res.push(o_res);
         }
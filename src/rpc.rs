@@ -0,0 +1,288 @@
+// JSON-RPC client for monero-wallet-rpc
+use crate::args;
+use clap::Parser;
+use diqwest::WithDigestAuth;
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// First retry waits this long, doubling after each attempt
+const RETRY_DELAY_MS: u64 = 500;
+
+tokio::task_local! {
+    /// wallet-rpc process the current wallet session runs on
+    static SLOT: usize;
+}
+
+/// Params for methods that take none
+#[derive(Serialize, Debug, Default)]
+pub struct NoParams {}
+
+/// Result of methods that return nothing, such as open_wallet
+#[derive(Deserialize, Debug, Default)]
+pub struct EmptyResult {}
+
+#[derive(Serialize)]
+struct JsonRpcRequest<'a, P> {
+    jsonrpc: &'a str,
+    id: &'a str,
+    method: &'a str,
+    params: P,
+}
+
+#[derive(Deserialize, Debug)]
+struct JsonRpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse<R> {
+    result: Option<R>,
+    error: Option<JsonRpcErrorObject>,
+}
+
+/// wallet-rpc error codes worth telling apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletError {
+    /// no wallet is open, or it was closed underneath us
+    NotOpen,
+    AlreadyExists,
+    InvalidPassword,
+    NotEnoughMoney,
+    DaemonBusy,
+    NoDaemonConnection,
+    Other,
+}
+
+impl WalletError {
+    pub fn from_code(code: i64) -> WalletError {
+        match code {
+            -3 => WalletError::DaemonBusy,
+            -13 => WalletError::NotOpen,
+            -17 | -37 => WalletError::NotEnoughMoney,
+            -21 => WalletError::AlreadyExists,
+            -22 => WalletError::InvalidPassword,
+            -38 => WalletError::NoDaemonConnection,
+            _ => WalletError::Other,
+        }
+    }
+}
+
+/// Why a wallet-rpc call failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcError {
+    /// the rpc server couldn't be reached, the request wasn't sent
+    Connect(String),
+    Timeout,
+    /// the request failed part way, it may or may not have run
+    Transport(String),
+    /// digest auth was rejected
+    Unauthorized,
+    Http(u16),
+    /// the body wasn't a json-rpc response of the expected type
+    Parse(String),
+    /// json-rpc error object returned by the wallet
+    Wallet { code: i64, message: String },
+}
+
+impl RpcError {
+    /// Only failures where the request never reached the wallet are retried,
+    /// a timed out transfer may still have gone through
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, RpcError::Connect(_))
+    }
+    /// The wallet answered with an error, as opposed to the rpc server being down
+    pub fn wallet_error(&self) -> Option<WalletError> {
+        match self {
+            RpcError::Wallet { code, .. } => Some(WalletError::from_code(*code)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Connect(e) => write!(f, "wallet-rpc unreachable: {}", e),
+            RpcError::Timeout => write!(f, "wallet-rpc timed out"),
+            RpcError::Transport(e) => write!(f, "wallet-rpc request failed: {}", e),
+            RpcError::Unauthorized => write!(f, "wallet-rpc rejected the credentials"),
+            RpcError::Http(status) => write!(f, "wallet-rpc returned http {}", status),
+            RpcError::Parse(e) => write!(f, "invalid wallet-rpc response: {}", e),
+            RpcError::Wallet { code, message } => write!(f, "wallet error {}: {}", code, message),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl From<diqwest::error::Error> for RpcError {
+    fn from(e: diqwest::error::Error) -> Self {
+        match e {
+            diqwest::error::Error::Reqwest(e) if e.is_timeout() => RpcError::Timeout,
+            diqwest::error::Error::Reqwest(e) if e.is_connect() => RpcError::Connect(e.to_string()),
+            e => RpcError::Transport(e.to_string()),
+        }
+    }
+}
+
+/// Decode a json-rpc response body, an error object wins over the result
+pub fn parse_response<R: DeserializeOwned>(body: &[u8]) -> Result<R, RpcError> {
+    let res: JsonRpcResponse<R> = serde_json::from_slice(body).map_err(|e| RpcError::Parse(e.to_string()))?;
+    match (res.error, res.result) {
+        (Some(e), _) => Err(RpcError::Wallet { code: e.code, message: e.message }),
+        (None, Some(r)) => Ok(r),
+        (None, None) => Err(RpcError::Parse(String::from("missing result"))),
+    }
+}
+
+/// One http client and set of credentials shared by every wallet-rpc call
+pub struct WalletRpcClient {
+    client: reqwest::Client,
+    url: String,
+    username: String,
+    credential: String,
+    retries: u32,
+}
+
+impl WalletRpcClient {
+    pub fn new(host: &str, username: String, credential: String, timeout: Duration, retries: u32) -> WalletRpcClient {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        WalletRpcClient { client, url: format!("{}/json_rpc", host), username, credential, retries }
+    }
+
+    async fn send<P: Serialize>(&self, method: &str, params: &P) -> Result<Vec<u8>, RpcError> {
+        let req = JsonRpcRequest { jsonrpc: "2.0", id: "0", method, params };
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&req)
+            .send_with_digest_auth(&self.username, &self.credential)
+            .await?;
+        match response.status() {
+            reqwest::StatusCode::UNAUTHORIZED => return Err(RpcError::Unauthorized),
+            s if !s.is_success() => return Err(RpcError::Http(s.as_u16())),
+            _ => (),
+        }
+        let body = response.bytes().await.map_err(|e| {
+            if e.is_timeout() { RpcError::Timeout } else { RpcError::Transport(e.to_string()) }
+        })?;
+        Ok(body.to_vec())
+    }
+
+    /// Call a wallet-rpc method once, without retrying
+    pub async fn try_call<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: &P) -> Result<R, RpcError> {
+        let body = self.send(method, params).await?;
+        debug!("{} response: {}", method, String::from_utf8_lossy(&body));
+        parse_response(&body)
+    }
+
+    /// Call a wallet-rpc method, retrying while the server can't be reached
+    pub async fn call<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: P) -> Result<R, RpcError> {
+        let mut attempt: u32 = 0;
+        loop {
+            match self.try_call(method, &params).await {
+                Err(e) if e.is_retryable() && attempt < self.retries => {
+                    warn!("{} failed, retrying: {}", method, e);
+                    tokio::time::sleep(Duration::from_millis(RETRY_DELAY_MS << attempt)).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Hosts of the wallet-rpc pool, on consecutive ports from the host port
+pub fn get_pool_hosts(host: &str, size: usize) -> Vec<String> {
+    let (base, port) = match host.rsplit_once(':').map(|(b, p)| (b, p.parse::<u16>())) {
+        Some((b, Ok(p))) => (b, p),
+        _ => return vec![String::from(host)],
+    };
+    (0..size.max(1) as u16).map(|i| format!("{}:{}", base, port + i)).collect()
+}
+
+/// Clients built from the command line configuration on first use
+fn pool() -> &'static [WalletRpcClient] {
+    static POOL: OnceLock<Vec<WalletRpcClient>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let args = args::Args::parse();
+        get_pool_hosts(&args.monero_rpc_host, args.monero_rpc_pool)
+            .iter()
+            .map(|host| {
+                WalletRpcClient::new(
+                    host,
+                    String::from(&args.monero_rpc_username),
+                    String::from(&args.monero_rpc_cred),
+                    Duration::from_secs(args.monero_rpc_timeout),
+                    args.monero_rpc_retries,
+                )
+            })
+            .collect()
+    })
+}
+
+/// Number of wallet-rpc processes
+pub fn pool_size() -> usize {
+    pool().len()
+}
+
+/// Run f with every call going to the pool's slot-th wallet-rpc
+pub async fn scope<F: Future>(slot: usize, f: F) -> F::Output {
+    SLOT.scope(slot % pool_size(), f).await
+}
+
+/// Client of the current wallet session, or the first wallet-rpc
+/// outside of a session
+pub fn client() -> &'static WalletRpcClient {
+    &pool()[SLOT.try_with(|s| *s).unwrap_or(0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Version {
+        version: i32,
+    }
+
+    #[test]
+    fn get_pool_hosts_test() {
+        assert_eq!(get_pool_hosts("http://localhost:38083", 1), vec!["http://localhost:38083"]);
+        assert_eq!(
+            get_pool_hosts("http://localhost:38083", 3),
+            vec!["http://localhost:38083", "http://localhost:38084", "http://localhost:38085"]
+        );
+        assert_eq!(get_pool_hosts("http://localhost:38083", 0), vec!["http://localhost:38083"]);
+    }
+
+    #[test]
+    fn parse_response_test() {
+        let ok = br#"{"id":"0","jsonrpc":"2.0","result":{"version":65562}}"#;
+        assert_eq!(parse_response::<Version>(ok), Ok(Version { version: 65562 }));
+        let err = br#"{"id":"0","jsonrpc":"2.0","error":{"code":-13,"message":"No wallet file"}}"#;
+        let e = parse_response::<Version>(err).unwrap_err();
+        assert_eq!(e.wallet_error(), Some(WalletError::NotOpen));
+        assert!(!e.is_retryable());
+        assert!(matches!(parse_response::<Version>(b"<html>"), Err(RpcError::Parse(_))));
+        assert!(matches!(parse_response::<Version>(br#"{"id":"0"}"#), Err(RpcError::Parse(_))));
+        let empty = br#"{"id":"0","jsonrpc":"2.0","result":{}}"#;
+        assert!(parse_response::<EmptyResult>(empty).is_ok());
+    }
+
+    #[test]
+    fn is_retryable_test() {
+        assert!(RpcError::Connect(String::from("refused")).is_retryable());
+        assert!(!RpcError::Timeout.is_retryable());
+        assert_eq!(RpcError::Timeout.wallet_error(), None);
+    }
+}
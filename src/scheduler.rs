@@ -0,0 +1,33 @@
+// Background order jobs, each on its own schedule_recv timer thread
+use crate::{args, order};
+use clap::Parser;
+use log::{debug, info};
+use std::future::Future;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Handle;
+
+/// Start the funding watcher and the order deadline job. Must be called
+/// from inside the runtime, jobs are driven on it from their own thread.
+pub fn start() {
+    let args = args::Args::parse();
+    let handle = Handle::current();
+    spawn(handle.clone(), "funding", args.funding_interval, order::check_all_funding);
+    spawn(handle, "deadlines", args.deadline_interval, order::enforce_deadlines);
+}
+
+/// Run the job every `interval` seconds, a run finishes before the next tick is read
+fn spawn<F, Fut>(handle: Handle, name: &'static str, interval: u64, job: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()>,
+{
+    info!("scheduling {} job every {}s", name, interval);
+    thread::spawn(move || {
+        let tick = schedule_recv::periodic(Duration::from_secs(interval));
+        while tick.recv().is_ok() {
+            debug!("running {} job", name);
+            handle.block_on(job());
+        }
+    });
+}
@@ -0,0 +1,193 @@
+// Product search index, stored beside the products in lmdb
+use crate::{db, models::*, product, rates, utils};
+use log::{debug, info};
+use std::collections::HashSet;
+
+/// Most results returned on a single page
+const MAX_PER_PAGE: usize = 50;
+
+/// Score weights for a term match in each product field
+const NAME_WEIGHT: u64 = 3;
+const TAG_WEIGHT: u64 = 2;
+const DESCRIPTION_WEIGHT: u64 = 1;
+
+/// Browse and keyword search filters
+#[derive(Debug, Default)]
+pub struct Query {
+    pub keywords: String,
+    pub category: Option<String>,
+    pub min_price: Option<i64>,
+    pub max_price: Option<i64>,
+    /// currency the price bounds are in, empty for piconero
+    pub currency: String,
+    pub vid: Option<String>,
+    pub in_stock: Option<bool>,
+    pub page: usize,
+    pub per_page: usize,
+}
+
+/// Key for the list of pids containing a term
+fn get_term_key(term: &str) -> String {
+    format!("si{}", term)
+}
+
+/// Key for the list of every indexed pid, used when browsing without
+/// keywords. Its prefix differs from the term keys so no term can match it.
+fn get_all_key() -> String {
+    String::from("sa")
+}
+
+/// Lowercase alphanumeric terms, single characters are dropped
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() > 1)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Categories and tags are compared case insensitively
+pub fn normalize(label: &str) -> String {
+    label.trim().to_lowercase()
+}
+
+fn get_terms(p: &Product) -> HashSet<String> {
+    let mut terms: HashSet<String> = HashSet::new();
+    terms.extend(tokenize(&p.p_name));
+    terms.extend(tokenize(&p.p_description));
+    for tag in &p.tags {
+        terms.extend(tokenize(tag));
+    }
+    terms
+}
+
+/// Add the product to the term lists
+pub fn index(p: &Product) {
+    debug!("indexing product {}", &p.pid);
+    let s = db::Interface::open();
+    for term in get_terms(p) {
+        db::Interface::append(&s.env, &s.handle, &get_term_key(&term), &p.pid);
+    }
+    db::Interface::append(&s.env, &s.handle, &get_all_key(), &p.pid);
+}
+
+/// Remove the product from the term lists
+pub fn unindex(p: &Product) {
+    debug!("removing product {} from index", &p.pid);
+    let s = db::Interface::open();
+    for term in get_terms(p) {
+        db::Interface::remove_from_list(&s.env, &s.handle, &get_term_key(&term), &p.pid);
+    }
+    db::Interface::remove_from_list(&s.env, &s.handle, &get_all_key(), &p.pid);
+}
+
+fn matches(p: &Product, q: &Query) -> bool {
+    let category = q.category.as_ref().is_none_or(|c| normalize(c) == p.category);
+    // price bounds only compare products priced in the same currency
+    let priced = (q.min_price.is_none() && q.max_price.is_none()) || rates::normalize(&q.currency) == p.currency;
+    let min_price = q.min_price.is_none_or(|min| p.p_price >= min);
+    let max_price = q.max_price.is_none_or(|max| p.p_price <= max);
+    let vendor = q.vid.as_ref().is_none_or(|vid| vid == &p.v_id);
+    let in_stock = q.in_stock.is_none_or(|in_stock| in_stock == p.in_stock);
+    category && priced && min_price && max_price && vendor && in_stock
+}
+
+/// Weighted count of keyword occurrences in the name, tags and description
+pub fn rank(p: &Product, terms: &[String]) -> u64 {
+    let name = tokenize(&p.p_name);
+    let description = tokenize(&p.p_description);
+    let tags: Vec<String> = p.tags.iter().flat_map(|t| tokenize(t)).collect();
+    let count = |field: &Vec<String>, term: &String| field.iter().filter(|t| *t == term).count() as u64;
+    terms.iter().map(|term| {
+        count(&name, term) * NAME_WEIGHT
+            + count(&tags, term) * TAG_WEIGHT
+            + count(&description, term) * DESCRIPTION_WEIGHT
+    }).sum()
+}
+
+/// Search products, returning the requested page and the total number of matches
+pub fn search(q: &Query) -> (Vec<Product>, usize) {
+    let terms: Vec<String> = tokenize(&q.keywords);
+    info!("searching products for {:?}", &terms);
+    let s = db::Interface::open();
+    let mut pids: HashSet<String> = HashSet::new();
+    let keys: Vec<String> = if terms.is_empty() {
+        vec![get_all_key()]
+    } else {
+        terms.iter().map(|t| get_term_key(t)).collect()
+    };
+    for key in keys {
+        let r = db::Interface::read(&s.env, &s.handle, &key);
+        pids.extend(r.split(',').filter(|pid| !pid.is_empty()).map(String::from));
+    }
+    let mut results: Vec<(u64, Product)> = pids
+        .iter()
+        .map(product::find)
+        .filter(|p| p.pid != utils::empty_string() && matches(p, q))
+        .map(|p| (rank(&p, &terms), p))
+        .collect();
+    results.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.p_name.cmp(&b.1.p_name)));
+    let total = results.len();
+    let per_page = q.per_page.clamp(1, MAX_PER_PAGE);
+    let page: Vec<Product> = results
+        .into_iter()
+        .skip(q.page * per_page)
+        .take(per_page)
+        .map(|r| r.1)
+        .collect();
+    (page, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_all_key_test() {
+        assert_ne!(get_term_key("all"), get_all_key());
+        assert!(!get_all_key().starts_with(&get_term_key("")));
+    }
+
+    #[test]
+    fn tokenize_test() {
+        let terms = tokenize("Green-Tea, 100g a LOOSE leaf!");
+        assert_eq!(terms, vec!["green", "tea", "100g", "loose", "leaf"]);
+    }
+
+    #[test]
+    fn rank_test() {
+        let p = Product {
+            p_name: String::from("Green tea"),
+            p_description: String::from("a green tea from the mountains"),
+            tags: vec![String::from("tea")],
+            ..Default::default()
+        };
+        let terms = tokenize("tea");
+        assert_eq!(rank(&p, &terms), NAME_WEIGHT + TAG_WEIGHT + DESCRIPTION_WEIGHT);
+        assert_eq!(rank(&p, &tokenize("coffee")), 0);
+    }
+
+    #[test]
+    fn matches_test() {
+        let p = Product {
+            v_id: String::from("vend1"),
+            category: String::from("tea"),
+            in_stock: true,
+            p_price: 500,
+            ..Default::default()
+        };
+        let q = Query {
+            category: Some(String::from(" Tea ")),
+            min_price: Some(100),
+            max_price: Some(500),
+            vid: Some(String::from("vend1")),
+            in_stock: Some(true),
+            ..Default::default()
+        };
+        assert!(matches(&p, &q));
+        assert!(!matches(&p, &Query { max_price: Some(499), ..Default::default() }));
+        assert!(!matches(&p, &Query { in_stock: Some(false), ..Default::default() }));
+        let usd = Query { max_price: Some(1000), currency: String::from("usd"), ..Default::default() };
+        assert!(!matches(&p, &usd));
+        assert!(matches(&Product { currency: String::from("USD"), ..p }, &usd));
+    }
+}
@@ -0,0 +1,135 @@
+// Shipping option repo/service layer
+use crate::{db, models::*, reqres, search, utils, vendor};
+use log::{debug, error, info};
+
+/// Key for the list of sids belonging to a vendor
+fn get_list_key(vid: &String) -> String {
+    format!("sl{}", vid)
+}
+
+fn write(s: &Shipping) {
+    let i = db::Interface::open();
+    db::Interface::delete(&i.env, &i.handle, &s.sid);
+    db::Interface::write(&i.env, &i.handle, &s.sid, &Shipping::to_db(s));
+}
+
+/// Apply the fields present in the request to the shipping option
+fn apply(s: Shipping, r: &reqres::ShippingRequest) -> Shipping {
+    Shipping {
+        name: r.name.clone().unwrap_or(s.name),
+        price: r.price.unwrap_or(s.price),
+        regions: r.regions.as_ref().map_or(s.regions, |regions| {
+            regions.iter().map(|region| search::normalize(region)).filter(|region| !region.is_empty()).collect()
+        }),
+        ..s
+    }
+}
+
+fn is_valid(s: &Shipping) -> bool {
+    !s.name.trim().is_empty() && s.price >= 0
+}
+
+/// Check that the option delivers to the region, no regions ships anywhere
+pub fn ships_to(s: &Shipping, region: &str) -> bool {
+    s.regions.is_empty() || s.regions.contains(&search::normalize(region))
+}
+
+/// Create a shipping option for the vendor
+pub fn create(vid: &String, address: &String, r: &reqres::ShippingRequest) -> Shipping {
+    let m_vendor: Vendor = vendor::find(vid);
+    if &m_vendor.v_xmr_address != address {
+        error!("vendor {} can't create shipping options", vid);
+        return Default::default();
+    }
+    let skeleton = Shipping {
+        sid: format!("ship{}", utils::generate_rnd()),
+        v_id: String::from(vid),
+        ..Default::default()
+    };
+    let new_shipping = apply(skeleton, r);
+    if !is_valid(&new_shipping) {
+        error!("invalid shipping option for vendor {}", vid);
+        return Default::default();
+    }
+    debug!("insert shipping: {:?}", &new_shipping);
+    write(&new_shipping);
+    let s = db::Interface::open();
+    db::Interface::append(&s.env, &s.handle, &get_list_key(vid), &new_shipping.sid);
+    new_shipping
+}
+
+/// Lookup shipping option
+pub fn find(sid: &String) -> Shipping {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &String::from(sid));
+    if r == utils::empty_string() {
+        error!("error finding shipping option");
+        return Default::default()
+    }
+    Shipping::from_db(String::from(sid), r)
+}
+
+/// Lookup all shipping options for a vendor
+pub fn find_all(vid: &String) -> Vec<Shipping> {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &get_list_key(vid));
+    if r == utils::empty_string() {
+        info!("no shipping options found for vendor {}", vid);
+        return Vec::new()
+    }
+    r.split(',')
+        .map(|sid| find(&String::from(sid)))
+        .filter(|s| s.sid != utils::empty_string())
+        .collect()
+}
+
+fn is_owner(s: &Shipping, address: &String) -> bool {
+    let m_vendor: Vendor = vendor::find(&s.v_id);
+    m_vendor.vid != utils::empty_string() && &m_vendor.v_xmr_address == address
+}
+
+/// Update a shipping option, only the owning vendor may do this
+pub fn modify(sid: &String, address: &String, r: &reqres::ShippingRequest) -> Shipping {
+    let f_shipping: Shipping = find(sid);
+    if f_shipping.sid == utils::empty_string() || !is_owner(&f_shipping, address) {
+        error!("shipping option {} can't be modified by {}", sid, address);
+        return Default::default();
+    }
+    let u_shipping = apply(f_shipping, r);
+    if !is_valid(&u_shipping) {
+        error!("invalid shipping option {}", sid);
+        return Default::default();
+    }
+    info!("modify shipping option {}", sid);
+    write(&u_shipping);
+    u_shipping
+}
+
+/// Delete a shipping option, only the owning vendor may do this.
+/// Orders keep the sid and amount they were created with.
+pub fn delete(sid: &String, address: &String) -> bool {
+    let f_shipping: Shipping = find(sid);
+    if f_shipping.sid == utils::empty_string() || !is_owner(&f_shipping, address) {
+        error!("shipping option {} can't be deleted by {}", sid, address);
+        return false;
+    }
+    info!("delete shipping option {}", sid);
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &f_shipping.sid);
+    db::Interface::remove_from_list(&s.env, &s.handle, &get_list_key(&f_shipping.v_id), sid);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ships_to_test() {
+        let anywhere: Shipping = Default::default();
+        assert!(ships_to(&anywhere, "US"));
+        let eu = Shipping { regions: vec![String::from("eu"), String::from("uk")], ..Default::default() };
+        assert!(ships_to(&eu, " EU "));
+        assert!(!ships_to(&eu, "us"));
+    }
+}
@@ -0,0 +1,211 @@
+// monero-wallet-rpc process supervisor
+use crate::{args, monero, rpc, utils};
+use clap::Parser;
+use log::{debug, error, info, warn};
+use rocket::fairing::AdHoc;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// First restart waits this long, doubling after each crash
+const RESTART_DELAY_MS: u64 = 1000;
+/// Longest wait between two restarts
+const MAX_RESTART_DELAY_MS: u64 = 60_000;
+/// A process that ran this long crashed on its own, the backoff starts over
+const STABLE_SECS: u64 = 60;
+/// Time wallet-rpc gets to exit after stop_wallet before it is killed
+const STOP_TIMEOUT_SECS: u64 = 30;
+/// Interval between start up readiness checks
+const READY_POLL_MS: u64 = 500;
+
+/// Delay before the next restart of a crashed process
+pub fn get_backoff(restarts: u32) -> Duration {
+    let delay = RESTART_DELAY_MS.saturating_mul(1 << restarts.min(16));
+    Duration::from_millis(delay.min(MAX_RESTART_DELAY_MS))
+}
+
+/// Port of a wallet-rpc host, e.g. 38083 for http://localhost:38083
+fn get_port(host: &str) -> String {
+    host.rsplit_once(':').map(|(_, p)| String::from(p)).unwrap_or_default()
+}
+
+/// monero-wallet-rpc command line, extra flags go last
+pub fn get_args(port: &str, args: &args::Args, stagenet: bool) -> Vec<String> {
+    let login = format!("{}:{}", args.monero_rpc_username, args.monero_rpc_cred);
+    let mut cmd: Vec<String> = [
+        "--rpc-bind-port", port,
+        "--wallet-dir", &args.monero_wallet_dir,
+        "--rpc-login", &login,
+        "--password", &args.monero_rpc_password,
+        "--daemon-address", &args.monero_rpc_daemon,
+    ]
+    .iter()
+    .map(|a| String::from(*a))
+    .collect();
+    if stagenet {
+        cmd.push(String::from("--stagenet"));
+    }
+    cmd.extend(args.monero_rpc_flags.split_whitespace().map(String::from));
+    cmd
+}
+
+fn shutdown() -> &'static watch::Sender<bool> {
+    static SHUTDOWN: OnceLock<watch::Sender<bool>> = OnceLock::new();
+    SHUTDOWN.get_or_init(|| watch::channel(false).0)
+}
+
+fn tasks() -> &'static Mutex<Vec<JoinHandle<()>>> {
+    static TASKS: OnceLock<Mutex<Vec<JoinHandle<()>>>> = OnceLock::new();
+    TASKS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Forward wallet-rpc output to the log
+async fn capture<R: AsyncRead + Unpin>(slot: usize, output: R) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        debug!("monero-wallet-rpc {}: {}", slot, line);
+    }
+}
+
+fn spawn(slot: usize, host: &str) -> std::io::Result<Child> {
+    let args = args::Args::parse();
+    let stagenet = utils::get_release_env() == utils::ReleaseEnvironment::Development;
+    let mut child = Command::new(&args.monero_rpc_bin)
+        .args(get_args(&get_port(host), &args, stagenet))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(capture(slot, stdout));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(capture(slot, stderr));
+    }
+    Ok(child)
+}
+
+/// Ask wallet-rpc to save the open wallet and exit, kill it if it doesn't
+async fn terminate(slot: usize, mut child: Child) {
+    info!("stopping monero-wallet-rpc {}", slot);
+    if let Err(e) = rpc::scope(slot, monero::stop_wallet()).await {
+        warn!("monero-wallet-rpc {} did not accept stop_wallet: {}", slot, e);
+    }
+    match tokio::time::timeout(Duration::from_secs(STOP_TIMEOUT_SECS), child.wait()).await {
+        Ok(status) => debug!("monero-wallet-rpc {} stopped: {:?}", slot, status),
+        Err(_) => {
+            warn!("monero-wallet-rpc {} did not stop, killing it", slot);
+            if let Err(e) = child.kill().await {
+                error!("failed to kill monero-wallet-rpc {}: {}", slot, e);
+            }
+        }
+    }
+}
+
+/// Keep one wallet-rpc running until shutdown, restarting it with a
+/// backoff whenever it exits
+async fn supervise(slot: usize, host: String) {
+    let mut stop = shutdown().subscribe();
+    let mut restarts: u32 = 0;
+    while !*stop.borrow() {
+        let started = Instant::now();
+        match spawn(slot, &host) {
+            Ok(mut child) => {
+                info!("started monero-wallet-rpc {} on {}", slot, &host);
+                tokio::select! {
+                    status = child.wait() => warn!("monero-wallet-rpc {} exited: {:?}", slot, status),
+                    _ = stop.changed() => return terminate(slot, child).await,
+                }
+            }
+            Err(e) => error!("monero-wallet-rpc {} failed to start: {}", slot, e),
+        }
+        if started.elapsed() >= Duration::from_secs(STABLE_SECS) {
+            restarts = 0;
+        }
+        let delay = get_backoff(restarts);
+        restarts = restarts.saturating_add(1);
+        warn!("restarting monero-wallet-rpc {} in {:?}", slot, delay);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => (),
+            _ = stop.changed() => return,
+        }
+    }
+}
+
+/// Start and supervise one monero-wallet-rpc per pool slot
+pub fn start() {
+    let args = args::Args::parse();
+    let handles = rpc::get_pool_hosts(&args.monero_rpc_host, args.monero_rpc_pool)
+        .into_iter()
+        .enumerate()
+        .map(|(slot, host)| tokio::spawn(supervise(slot, host)));
+    if let Ok(mut t) = tasks().lock() {
+        t.extend(handles);
+    }
+}
+
+/// Poll every wallet-rpc until it answers or the start up timeout passes
+pub async fn wait_until_ready() -> bool {
+    let timeout = args::Args::parse().monero_rpc_ready_timeout;
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    for slot in 0..rpc::pool_size() {
+        while !rpc::scope(slot, monero::is_ready()).await {
+            if Instant::now() >= deadline {
+                error!("monero-wallet-rpc {} is not ready after {}s", slot, timeout);
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(READY_POLL_MS)).await;
+        }
+        debug!("monero-wallet-rpc {} is ready", slot);
+    }
+    true
+}
+
+/// Stop every wallet-rpc and wait for the supervisors to finish
+pub async fn stop() {
+    shutdown().send_replace(true);
+    let handles: Vec<JoinHandle<()>> = tasks().lock().map(|mut t| t.drain(..).collect()).unwrap_or_default();
+    for handle in handles {
+        if let Err(e) = handle.await {
+            error!("monero-wallet-rpc supervisor failed: {}", e);
+        }
+    }
+}
+
+/// Stops wallet-rpc when Rocket shuts down
+pub fn fairing() -> AdHoc {
+    AdHoc::on_shutdown("monero-wallet-rpc", |_| Box::pin(stop()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_backoff_test() {
+        assert_eq!(get_backoff(0), Duration::from_secs(1));
+        assert_eq!(get_backoff(3), Duration::from_secs(8));
+        assert_eq!(get_backoff(10), Duration::from_secs(60));
+        assert_eq!(get_backoff(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn get_args_test() {
+        let args = args::Args {
+            monero_rpc_username: String::from("user"),
+            monero_rpc_cred: String::from("pass"),
+            monero_wallet_dir: String::from("/var/wallets"),
+            monero_rpc_flags: String::from(" --log-level 1  --trusted-daemon"),
+            ..Default::default()
+        };
+        let cmd = get_args(&get_port("http://localhost:38083"), &args, true);
+        assert_eq!(cmd[..4], ["--rpc-bind-port", "38083", "--wallet-dir", "/var/wallets"]);
+        assert_eq!(cmd[5], "user:pass");
+        assert_eq!(cmd[10..], ["--stagenet", "--log-level", "1", "--trusted-daemon"]);
+        assert!(!get_args("38083", &args, false).contains(&String::from("--stagenet")));
+    }
+}
@@ -1,8 +1,8 @@
 use hex;
 use rand_core::RngCore;
 use clap::Parser;
-use crate::{args, i2p, monero};
-use log::info;
+use crate::{args, bond, commission, i2p, rpc, scheduler, supervisor, wallet};
+use log::{error, info};
 use std::time::Duration;
 
 #[derive(Debug, PartialEq)]
@@ -23,6 +23,7 @@ impl ReleaseEnvironment {
 #[derive(Debug)]
 pub enum LoginType {
     Customer,
+    Mediator,
     Vendor,
 }
 
@@ -30,6 +31,7 @@ impl LoginType {
     pub fn value(&self) -> String {
         match *self {
             LoginType::Customer => String::from("customer"),
+            LoginType::Mediator => String::from("mediator"),
             LoginType::Vendor => String::from("vendor"),
         }
     }
@@ -74,14 +76,34 @@ pub fn get_jwt_secret_key() -> Vec<u8> {
     key.into_bytes()
 }
 
+/// Marketplace wallet filename for bond subaddresses
+pub fn get_market_wallet() -> String {
+    let args = args::Args::parse();
+    args.market_wallet
+}
+
 pub fn empty_string() -> String { String::from("") }
 
+/// Decode hex encoded free text from the db, empty on failure
+pub fn decode_hex(data: &str) -> String {
+    let bytes = hex::decode(data).unwrap_or_default();
+    String::from_utf8(bytes).unwrap_or_default()
+}
+
 pub async fn start_up() {
     info!("mpi2p is starting up");
-    monero::start_rpc().await;
-    // wait for rpc server for a bit
-    tokio::time::sleep(Duration::new(5, 0)).await;
-    monero::check_rpc_connection().await;
+    supervisor::start();
+    let ready = supervisor::wait_until_ready().await;
+    if ready && (bond::is_required() || commission::is_enabled()) {
+        // create the market wallet for bonds and commission
+        let wallet = get_market_wallet();
+        match wallet::create(&wallet).await {
+            Ok(_) => info!("created market wallet {}", &wallet),
+            Err(e) if e.wallet_error() == Some(rpc::WalletError::AlreadyExists) => (),
+            Err(e) => error!("error creating market wallet {}: {}", &wallet, e),
+        }
+    }
+    scheduler::start();
     let env: String = get_release_env().value();
     let dev: String = ReleaseEnvironment::Development.value();
     if env != dev {
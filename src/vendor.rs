@@ -1,12 +1,6 @@
-// use diesel::prelude::*;
-// use diesel::pg::PgConnection;
-
-// use crate::auth;
-// use crate::utils;
-// use crate::monero;
-// use crate::schema;
-// use crate::models::*;
-// use log::{debug, error, info};
+// Vendor repo/service layer
+use crate::{auth, bond, db, dispute, feedback, models::*, monero, order, utils};
+use log::{debug, error, info};
 
 // #[derive(Debug)]
 // enum UpdateType {
@@ -27,82 +21,179 @@
 //     }
 // }
 
-// /// Create skeleton for vendor
-// async fn create(
-//     conn: &mut PgConnection,
-//     v_xmr_address: &str,
-//     v_name: &str,
-//     v_pgp: &str,
-//     v_description: &str,
-//     active: &bool,
-// ) -> Vendor {
-//     use crate::schema::vendors;
-//     let f_vid: String = format!("V{}", utils::generate_rnd());
-//     let new_vendor = NewVendor {
-//         vid: &f_vid,
-//         v_xmr_address,
-//         v_name,
-//         v_description,
-//         v_pgp,
-//         active,
-//     };
-//     debug!("insert vendor: {:?}", new_vendor);
-//     diesel::insert_into(vendors::table)
-//         .values(&new_vendor)
-//         .get_result(conn)
-//         .expect("error saving new vendor")
-// }
+/// Create skeleton for vendor
+fn create(address: &String) -> Vendor {
+    let f_vid: String = format!("vend{}", utils::generate_rnd());
+    let new_vendor = Vendor {
+        vid: String::from(&f_vid),
+        v_xmr_address: String::from(address),
+        v_name: utils::empty_string(),
+        v_description: utils::empty_string(),
+        v_pgp: utils::empty_string(),
+        active: false,
+        created: chrono::offset::Utc::now().timestamp(),
+    };
+    debug!("insert vendor: {:?}", &new_vendor);
+    let s = db::Interface::open();
+    let k = &new_vendor.vid;
+    db::Interface::write(&s.env, &s.handle, k, &Vendor::to_db(&new_vendor));
+    new_vendor
+}
 
-// /// Verifies vendor signature against stored auth
-// pub async fn verify_login(address: String, signature: String) -> Authorization {
-//     use self::schema::vendors::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let f_address = String::from(&address);
-//     let f_auth: Authorization = auth::find(f_address).await;
-//     let data: String = String::from(&f_auth.rnd);
-//     if f_auth.xmr_address == String::from("") {
-//         return auth::create(connection, address).await;
-//     }
-//     let sig_address: String = monero::verify_signature(address, data, String::from(&signature)).await;
-//     if sig_address == utils::ApplicationErrors::LoginError.value() {
-//         return f_auth;
-//     }
-//     let result = vendors
-//         .filter(schema::vendors::v_xmr_address.eq(&sig_address))
-//         .first::<Vendor>(connection);
-//     match result {
-//         Ok(r) => {
-//             if r.v_xmr_address != String::from("") {
-//                 let m_access = auth::verify_access(&r.v_xmr_address, &signature).await;
-//                 if !m_access { return Default::default() }
-//                 return f_auth;
-//             }
-//             error!("error creating vendor");
-//             Default::default()
-//         }
-//         _ => {
-//             info!("creating new vendor");
-//             create(connection, &sig_address, "", "", "", &false).await;
-//             return f_auth;
-//         }
-//     }
-// }
+/// Vendor lookup
+pub fn find(vid: &String) -> Vendor {
+    let s = db::Interface::open();
+    let r = db::Interface::read(&s.env, &s.handle, &String::from(vid));
+    if r == utils::empty_string() {
+        return Default::default()
+    }
+    Vendor::from_db(String::from(vid), r)
+}
 
-// /// Vendor lookup
-// pub async fn find(address: String) -> Vendor {
-//     use self::schema::vendors::dsl::*;
-//     let connection = &mut utils::establish_pgdb_connection().await;
-//     let results = vendors
-//         .filter(schema::vendors::v_xmr_address.eq(address))
-//         .first::<Vendor>(connection);
-//     match results {
-//         Ok(r) => r,
-//         _ => {
-//             error!("error finding vendor");
-//             Default::default()
-//         }
-//     }
-// }
+/// Performs the signature verfication against stored auth
+pub async fn verify_login(
+    address: String,
+    aid: String,
+    cvid: String,
+    signature: String,
+) -> Authorization {
+    let f_auth: Authorization = auth::find(&aid);
+    if f_auth.xmr_address == utils::empty_string() {
+        return auth::create(&address);
+    }
+    let data: String = String::from(&f_auth.rnd);
+    match monero::verify_signature(String::from(&address), data, String::from(&signature)).await {
+        Ok(true) => (),
+        Ok(false) => return f_auth,
+        Err(e) => {
+            error!("can't verify signature for {}: {}", &address, e);
+            return f_auth;
+        }
+    }
+    let f_vend: Vendor = find(&cvid);
+    if f_vend.v_xmr_address == utils::empty_string() {
+        info!("creating new vendor");
+        let v: Vendor = create(&address);
+        // update auth with cvid
+        let u_auth = Authorization::update_cvid(f_auth, String::from(&v.vid));
+        let s = db::Interface::open();
+        db::Interface::delete(&s.env, &s.handle, &u_auth.aid);
+        db::Interface::write(&s.env, &s.handle, &u_auth.aid, &Authorization::to_db(&u_auth));
+        u_auth
+    } else {
+        info!("returning vendor");
+        let m_access = auth::verify_access(&address, &signature).await;
+        if !m_access { return Default::default() }
+        f_auth
+    }
+}
+
+/// Turn on the active flag once the bond requirement is met
+pub async fn activate(vid: &String) -> Vendor {
+    let f_vend: Vendor = find(vid);
+    if f_vend.vid == utils::empty_string() {
+        error!("vendor {} not found", vid);
+        return f_vend;
+    }
+    let required = bond::is_required();
+    // only check the wallet when a bond is required
+    let m_bond: Bond = if required { bond::check_payment(vid).await } else { Default::default() };
+    if !bond::is_bonded(required, &m_bond) {
+        info!("vendor {} bond is not paid", vid);
+        return f_vend;
+    }
+    info!("activating vendor {}", vid);
+    set_active(f_vend, true)
+}
+
+/// Write the active flag, bond resolution also deactivates here
+pub fn set_active(v: Vendor, active: bool) -> Vendor {
+    let u_vend = Vendor::update_active(v, active);
+    let s = db::Interface::open();
+    db::Interface::delete(&s.env, &s.handle, &u_vend.vid);
+    db::Interface::write(&s.env, &s.handle, &u_vend.vid, &Vendor::to_db(&u_vend));
+    u_vend
+}
+
+/// Reputation statistics computed from order and dispute records
+#[derive(Debug, Default, PartialEq)]
+pub struct VendorStats {
+    pub total_orders: u64,
+    pub completed_orders: u64,
+    pub disputed_orders: u64,
+    /// disputed orders / funded orders
+    pub dispute_rate: f64,
+    /// average seconds between funding and shipping
+    pub avg_ship_time: i64,
+    /// seconds since the vendor was created
+    pub account_age: i64,
+    pub feedback_count: u64,
+    /// average customer rating (1 - 5)
+    pub feedback_score: f64,
+}
+
+/// Build stats from a vendor's orders and the orids that have a dispute
+fn compute_stats(orders: &[Order], disputed: &[String], created: i64, now: i64) -> VendorStats {
+    let total_orders = orders.len() as u64;
+    let completed_orders = orders
+        .iter()
+        .filter(|o| {
+            o.o_status == order::OrderStatus::Delivered.value()
+                || o.o_status == order::OrderStatus::Released.value()
+                || o.o_status == order::OrderStatus::Settled.value()
+        })
+        .count() as u64;
+    let disputed_orders = orders.iter().filter(|o| disputed.contains(&o.orid)).count() as u64;
+    // unfunded orders cost nothing to create, they don't count against the vendor
+    let funded: Vec<(&Order, i64)> = orders
+        .iter()
+        .map(|o| (o, order::get_status_date(o, order::OrderStatus::Funded)))
+        .filter(|(_, f)| *f > 0)
+        .collect();
+    let dispute_rate = if funded.is_empty() {
+        0.0
+    } else {
+        disputed_orders as f64 / funded.len() as f64
+    };
+    let ship_times: Vec<i64> = funded
+        .iter()
+        .filter(|(o, f)| o.o_ship_date > 0 && o.o_ship_date >= *f)
+        .map(|(o, f)| o.o_ship_date - f)
+        .collect();
+    let avg_ship_time = if ship_times.is_empty() {
+        0
+    } else {
+        ship_times.iter().sum::<i64>() / ship_times.len() as i64
+    };
+    let account_age = if created > 0 { now - created } else { 0 };
+    VendorStats {
+        total_orders,
+        completed_orders,
+        disputed_orders,
+        dispute_rate,
+        avg_ship_time,
+        account_age,
+        ..Default::default()
+    }
+}
+
+/// Public vendor profile statistics
+pub fn stats(v: &Vendor) -> VendorStats {
+    let orders: Vec<Order> = order::find_all(&v.vid);
+    let disputed: Vec<String> = orders
+        .iter()
+        .map(|o| dispute::find(&o.orid))
+        .filter(|d| d.orid != utils::empty_string())
+        .map(|d| d.orid)
+        .collect();
+    let now: i64 = chrono::offset::Utc::now().timestamp();
+    let m_feedback: Vec<Feedback> = feedback::find_all(&v.vid);
+    VendorStats {
+        feedback_count: m_feedback.len() as u64,
+        feedback_score: feedback::score(&m_feedback),
+        ..compute_stats(&orders, &disputed, v.created, now)
+    }
+}
 
 // /// Update vendor info
 // pub async fn modify(_id: String, data: String, update_type: i32) -> Vendor {
@@ -147,3 +238,55 @@
 //     }
 //     Default::default()
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_order(orid: &str, status: String, funded: i64, o_ship_date: i64) -> Order {
+        let o_history = if funded > 0 {
+            vec![StatusChange {
+                status: order::OrderStatus::Funded.value(),
+                role: order::Role::System.value(),
+                timestamp: funded,
+            }]
+        } else {
+            Vec::new()
+        };
+        Order {
+            orid: String::from(orid),
+            o_status: status,
+            o_date: 50,
+            o_ship_date,
+            o_history,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compute_stats_test() {
+        let orders = vec![
+            test_order("o1", order::OrderStatus::Delivered.value(), 100, 200),
+            test_order("o2", order::OrderStatus::Shipped.value(), 100, 400),
+            test_order("o3", order::OrderStatus::Created.value(), 0, 0),
+            test_order("o4", order::OrderStatus::Released.value(), 100, 300),
+            test_order("o5", order::OrderStatus::Settled.value(), 100, 300),
+            test_order("o6", order::OrderStatus::Expired.value(), 0, 0),
+            test_order("o7", order::OrderStatus::Cancelled.value(), 0, 0),
+        ];
+        let disputed = vec![String::from("o2"), String::from("o5")];
+        let stats = compute_stats(&orders, &disputed, 50, 1050);
+        assert_eq!(stats.total_orders, 7);
+        assert_eq!(stats.completed_orders, 3);
+        assert_eq!(stats.disputed_orders, 2);
+        assert_eq!(stats.dispute_rate, 0.5);
+        assert_eq!(stats.avg_ship_time, 200);
+        assert_eq!(stats.account_age, 1000);
+    }
+
+    #[test]
+    fn compute_stats_empty_test() {
+        let stats = compute_stats(&Vec::new(), &Vec::new(), 0, 1050);
+        assert_eq!(stats, Default::default());
+    }
+}
@@ -0,0 +1,113 @@
+// Wallet sessions on monero-wallet-rpc
+use crate::{monero, rpc};
+use log::{debug, error, warn};
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// wallet-rpc process a wallet always runs on, so it is never open in two
+/// processes at once
+pub fn get_slot(name: &str, size: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    (hasher.finish() % size.max(1) as u64) as usize
+}
+
+/// One lock per wallet-rpc process, it can only have one wallet open
+fn locks() -> &'static [Arc<Mutex<()>>] {
+    static LOCKS: OnceLock<Vec<Arc<Mutex<()>>>> = OnceLock::new();
+    LOCKS.get_or_init(|| (0..rpc::pool_size()).map(|_| Arc::new(Mutex::new(()))).collect())
+}
+
+/// An open wallet that holds its wallet-rpc process until it is closed
+struct Session {
+    name: String,
+    slot: usize,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Session {
+    /// Wait for the wallet's process, then open or create the wallet
+    async fn open(name: &str, create: bool) -> Result<Session, rpc::RpcError> {
+        let slot = get_slot(name, rpc::pool_size());
+        let guard = Arc::clone(&locks()[slot]).lock_owned().await;
+        debug!("wallet {} has wallet-rpc {}", name, slot);
+        let opened = if create {
+            rpc::scope(slot, monero::create_wallet(String::from(name))).await
+        } else {
+            rpc::scope(slot, monero::open_wallet(String::from(name))).await
+        };
+        // the guard is released without a close when the open failed
+        opened.map(|_| Session { name: String::from(name), slot, guard: Some(guard) })
+    }
+
+    async fn close(mut self) -> Result<(), rpc::RpcError> {
+        let guard = self.guard.take();
+        let closed = rpc::scope(self.slot, monero::close_wallet(String::from(&self.name))).await;
+        drop(guard);
+        closed
+    }
+}
+
+impl Drop for Session {
+    /// Close the wallet when the session is dropped without closing,
+    /// e.g. when the request running it was cancelled
+    fn drop(&mut self) {
+        let guard = match self.guard.take() {
+            Some(g) => g,
+            None => return,
+        };
+        let (name, slot) = (String::from(&self.name), self.slot);
+        warn!("wallet {} dropped while open", &name);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = rpc::scope(slot, monero::close_wallet(String::from(&name))).await {
+                        error!("error closing wallet {}: {}", &name, e);
+                    }
+                    drop(guard);
+                });
+            }
+            Err(_) => error!("no runtime to close wallet {}", &name),
+        }
+    }
+}
+
+/// Open a wallet, run f against it and close it again. Other sessions on
+/// the same wallet-rpc wait until then. Errors opening the wallet and
+/// from f are returned, a failed close is only logged.
+pub async fn with<T, F>(name: &str, f: F) -> Result<T, rpc::RpcError>
+where
+    F: Future<Output = Result<T, rpc::RpcError>>,
+{
+    let session = Session::open(name, false).await?;
+    let output = rpc::scope(session.slot, f).await;
+    if let Err(e) = session.close().await {
+        error!("error closing wallet {}: {}", name, e);
+    }
+    output
+}
+
+/// Create a wallet, which wallet-rpc leaves open, and close it
+pub async fn create(name: &str) -> Result<(), rpc::RpcError> {
+    let session = Session::open(name, true).await?;
+    if let Err(e) = session.close().await {
+        error!("error closing wallet {}: {}", name, e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_slot_test() {
+        assert_eq!(get_slot("ord1", 1), 0);
+        assert_eq!(get_slot("ord1", 0), 0);
+        assert_eq!(get_slot("ord1", 4), get_slot("ord1", 4));
+        assert!((0..100).all(|i| get_slot(&format!("ord{}", i), 3) < 3));
+    }
+}